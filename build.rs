@@ -0,0 +1,43 @@
+use cargo_lock::Lockfile;
+use std::path::Path;
+use std::process::Command;
+
+// records the versions the mock was built with for the ABI check of the plugins
+fn main() {
+    let lockfile_path = Path::new(&std::env::var("CARGO_MANIFEST_DIR").unwrap()).join("Cargo.lock");
+    println!("cargo:rerun-if-changed={}", lockfile_path.display());
+
+    let interface_version = Lockfile::load(&lockfile_path)
+        .ok()
+        .and_then(|lockfile| {
+            lockfile
+                .packages
+                .into_iter()
+                .find(|package| package.name.as_str() == "agave-geyser-plugin-interface")
+        })
+        .map(|package| package.version.to_string())
+        .unwrap_or_else(|| {
+            println!("cargo:warning=agave-geyser-plugin-interface not found in Cargo.lock");
+            String::new()
+        });
+    println!(
+        "cargo:rustc-env=MOCK_PLUGIN_INTERFACE_VERSION={}",
+        interface_version
+    );
+
+    // release: 1.78.0
+    // commit-hash: 9b00956e56009bab2aa15d7bff10916599e3d6d6
+    let rustc = std::env::var("RUSTC").unwrap();
+    let output = Command::new(rustc).arg("-vV").output().unwrap();
+    let version_info = String::from_utf8(output.stdout).unwrap();
+    for (key, env) in [
+        ("release: ", "MOCK_RUSTC_RELEASE"),
+        ("commit-hash: ", "MOCK_RUSTC_COMMIT_HASH"),
+    ] {
+        let value = version_info
+            .lines()
+            .find_map(|line| line.strip_prefix(key))
+            .unwrap_or_default();
+        println!("cargo:rustc-env={}={}", env, value);
+    }
+}
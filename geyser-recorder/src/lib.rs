@@ -0,0 +1,175 @@
+use crate::recording::{
+    RecordedAccount, RecordedBlockMeta, RecordedEntry, RecordedKind, RecordedSlot,
+    RecordedTransaction, RecordingWriter,
+};
+use agave_geyser_plugin_interface::geyser_plugin_interface::{
+    GeyserPlugin, GeyserPluginError, ReplicaAccountInfoVersions, ReplicaBlockInfoVersions,
+    ReplicaEntryInfoVersions, ReplicaTransactionInfoVersions, Result, SlotStatus,
+};
+use log::{error, info};
+use serde_derive::Deserialize;
+use solana_sdk::clock::Slot;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+pub mod recording;
+
+#[derive(Deserialize)]
+struct RecorderConfig {
+    // file the plugin calls are written to
+    output: String,
+}
+
+// output.1, output.2, ...
+fn next_free_path(output: &str) -> PathBuf {
+    (1..)
+        .map(|n| PathBuf::from(format!("{}.{}", output, n)))
+        .find(|path| !path.exists())
+        .unwrap()
+}
+
+// captures all plugin calls of a validator into a file which can be replayed with --replay
+#[derive(Debug, Default)]
+pub struct GeyserRecorderPlugin {
+    writer: Option<Mutex<RecordingWriter>>,
+}
+
+impl GeyserRecorderPlugin {
+    fn record(&self, kind: RecordedKind) -> Result<()> {
+        let Some(writer) = self.writer.as_ref() else {
+            return Ok(());
+        };
+        writer
+            .lock()
+            .unwrap()
+            .write(kind)
+            .map_err(|err| GeyserPluginError::Custom(err))
+    }
+}
+
+impl GeyserPlugin for GeyserRecorderPlugin {
+    fn name(&self) -> &'static str {
+        "GeyserRecorderPlugin"
+    }
+
+    fn on_load(&mut self, config_file: &str, is_reload: bool) -> Result<()> {
+        let contents = std::fs::read_to_string(config_file)?;
+        // json5 like the validator, so comments and trailing commas are fine
+        let config: RecorderConfig =
+            json5::from_str(&contents).map_err(|err| GeyserPluginError::ConfigFileReadError {
+                msg: err.to_string(),
+            })?;
+
+        // a reload must not wipe what was recorded so far
+        let output = if is_reload {
+            next_free_path(&config.output)
+        } else {
+            PathBuf::from(&config.output)
+        };
+        info!("recording geyser plugin calls to {}", output.display());
+        self.writer = Some(Mutex::new(RecordingWriter::create(&output)?));
+        Ok(())
+    }
+
+    fn on_unload(&mut self) {
+        if let Some(writer) = self.writer.take() {
+            // must not take down the validator
+            if let Err(err) = writer.lock().unwrap().flush() {
+                error!("failed to flush the geyser recording: {}", err);
+            }
+        }
+    }
+
+    // agave 2.0 only sends the latest versions of the replica infos
+    fn update_account(
+        &self,
+        account: ReplicaAccountInfoVersions,
+        slot: Slot,
+        is_startup: bool,
+    ) -> Result<()> {
+        let ReplicaAccountInfoVersions::V0_0_3(account) = account else {
+            return Ok(());
+        };
+        self.record(RecordedKind::Account(RecordedAccount::from_replica(
+            account, slot, is_startup,
+        )))
+    }
+
+    fn notify_end_of_startup(&self) -> Result<()> {
+        self.record(RecordedKind::EndOfStartup)
+    }
+
+    fn update_slot_status(
+        &self,
+        slot: Slot,
+        parent: Option<u64>,
+        status: SlotStatus,
+    ) -> Result<()> {
+        self.record(RecordedKind::Slot(RecordedSlot {
+            slot,
+            parent,
+            status: status.into(),
+        }))?;
+        // keep the recording usable if the validator gets killed
+        if let Some(writer) = self.writer.as_ref() {
+            writer
+                .lock()
+                .unwrap()
+                .flush()
+                .map_err(|err| GeyserPluginError::Custom(Box::new(err)))?;
+        }
+        Ok(())
+    }
+
+    fn notify_transaction(
+        &self,
+        transaction: ReplicaTransactionInfoVersions,
+        slot: Slot,
+    ) -> Result<()> {
+        let ReplicaTransactionInfoVersions::V0_0_2(transaction) = transaction else {
+            return Ok(());
+        };
+        let transaction = RecordedTransaction::from_replica(transaction, slot)
+            .map_err(|msg| GeyserPluginError::TransactionUpdateError { msg })?;
+        self.record(RecordedKind::Transaction(Box::new(transaction)))
+    }
+
+    fn notify_entry(&self, entry: ReplicaEntryInfoVersions) -> Result<()> {
+        let ReplicaEntryInfoVersions::V0_0_2(entry) = entry else {
+            return Ok(());
+        };
+        self.record(RecordedKind::Entry(RecordedEntry::from_replica(entry)))
+    }
+
+    fn notify_block_metadata(&self, blockinfo: ReplicaBlockInfoVersions) -> Result<()> {
+        let ReplicaBlockInfoVersions::V0_0_4(blockinfo) = blockinfo else {
+            return Ok(());
+        };
+        self.record(RecordedKind::BlockMeta(RecordedBlockMeta::from_replica(
+            blockinfo,
+        )))
+    }
+
+    fn account_data_notifications_enabled(&self) -> bool {
+        true
+    }
+
+    fn transaction_notifications_enabled(&self) -> bool {
+        true
+    }
+
+    fn entry_notifications_enabled(&self) -> bool {
+        true
+    }
+}
+
+#[no_mangle]
+#[allow(improper_ctypes_definitions)]
+/// # Safety
+///
+/// The Solana validator and this plugin must be compiled with the same Rust compiler version and Solana core version.
+/// Loading this plugin with different compiler version or different Solana core version will cause undefined behavior.
+pub unsafe extern "C" fn _create_plugin() -> *mut dyn GeyserPlugin {
+    let plugin: Box<dyn GeyserPlugin> = Box::<GeyserRecorderPlugin>::default();
+    Box::into_raw(plugin)
+}
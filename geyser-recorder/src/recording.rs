@@ -0,0 +1,319 @@
+use agave_geyser_plugin_interface::geyser_plugin_interface::{
+    ReplicaAccountInfoV3, ReplicaBlockInfoV4, ReplicaEntryInfoV2, ReplicaTransactionInfoV2,
+    SlotStatus,
+};
+use serde::de::DeserializeOwned;
+use serde_derive::{Deserialize, Serialize};
+use solana_sdk::clock::{Epoch, Slot, UnixTimestamp};
+use solana_sdk::hash::Hash;
+use solana_sdk::message::v0::LoadedAddresses;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::Signature;
+use solana_sdk::transaction::VersionedTransaction;
+use solana_storage_proto::StoredTransactionStatusMeta;
+use solana_transaction_status::RewardsAndNumPartitions;
+use std::fs::File;
+use std::io::{BufReader, BufWriter, ErrorKind, Read, Write};
+use std::path::Path;
+use std::time::Instant;
+
+// file header, bump the version on any change of the recorded types
+const MAGIC: &[u8; 8] = b"GEYSREC1";
+
+// one plugin callback; elapsed since the recording was started
+#[derive(Serialize, Deserialize)]
+pub struct RecordedEvent {
+    pub elapsed_us: u64,
+    pub kind: RecordedKind,
+}
+
+#[derive(Serialize, Deserialize)]
+pub enum RecordedKind {
+    Account(RecordedAccount),
+    Slot(RecordedSlot),
+    Transaction(Box<RecordedTransaction>),
+    Entry(RecordedEntry),
+    BlockMeta(RecordedBlockMeta),
+    EndOfStartup,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RecordedAccount {
+    pub slot: Slot,
+    pub pubkey: Pubkey,
+    pub lamports: u64,
+    pub owner: Pubkey,
+    pub executable: bool,
+    pub rent_epoch: Epoch,
+    pub data: Vec<u8>,
+    pub write_version: u64,
+    pub is_startup: bool,
+}
+
+impl RecordedAccount {
+    pub fn from_replica(account: &ReplicaAccountInfoV3, slot: Slot, is_startup: bool) -> Self {
+        Self {
+            slot,
+            pubkey: account.pubkey.try_into().unwrap(),
+            lamports: account.lamports,
+            owner: account.owner.try_into().unwrap(),
+            executable: account.executable,
+            rent_epoch: account.rent_epoch,
+            data: account.data.to_vec(),
+            write_version: account.write_version,
+            is_startup,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum RecordedSlotStatus {
+    Processed,
+    Rooted,
+    Confirmed,
+}
+
+impl From<SlotStatus> for RecordedSlotStatus {
+    fn from(status: SlotStatus) -> Self {
+        match status {
+            SlotStatus::Processed => RecordedSlotStatus::Processed,
+            SlotStatus::Rooted => RecordedSlotStatus::Rooted,
+            SlotStatus::Confirmed => RecordedSlotStatus::Confirmed,
+        }
+    }
+}
+
+impl From<RecordedSlotStatus> for SlotStatus {
+    fn from(status: RecordedSlotStatus) -> Self {
+        match status {
+            RecordedSlotStatus::Processed => SlotStatus::Processed,
+            RecordedSlotStatus::Rooted => SlotStatus::Rooted,
+            RecordedSlotStatus::Confirmed => SlotStatus::Confirmed,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RecordedSlot {
+    pub slot: Slot,
+    pub parent: Option<Slot>,
+    pub status: RecordedSlotStatus,
+}
+
+// enough to rebuild the SanitizedTransaction including lookup table addresses
+#[derive(Serialize, Deserialize)]
+pub struct RecordedTransaction {
+    pub slot: Slot,
+    pub signature: Signature,
+    pub is_vote: bool,
+    pub index: usize,
+    pub transaction: VersionedTransaction,
+    pub message_hash: Hash,
+    pub loaded_addresses: LoadedAddresses,
+    pub transaction_status_meta: StoredTransactionStatusMeta,
+}
+
+impl RecordedTransaction {
+    // fails if the status meta cannot be stored, e.g. for unknown transaction errors
+    pub fn from_replica(
+        transaction: &ReplicaTransactionInfoV2,
+        slot: Slot,
+    ) -> Result<Self, String> {
+        let transaction_status_meta =
+            StoredTransactionStatusMeta::try_from(transaction.transaction_status_meta.clone())
+                .map_err(|err| err.to_string())?;
+        Ok(Self {
+            slot,
+            signature: *transaction.signature,
+            is_vote: transaction.is_vote,
+            index: transaction.index,
+            transaction: transaction.transaction.to_versioned_transaction(),
+            message_hash: *transaction.transaction.message_hash(),
+            loaded_addresses: transaction.transaction.get_loaded_addresses(),
+            transaction_status_meta,
+        })
+    }
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct RecordedEntry {
+    pub slot: Slot,
+    pub index: usize,
+    pub num_hashes: u64,
+    pub hash: Hash,
+    pub executed_transaction_count: u64,
+    pub starting_transaction_index: usize,
+}
+
+impl RecordedEntry {
+    pub fn from_replica(entry: &ReplicaEntryInfoV2) -> Self {
+        Self {
+            slot: entry.slot,
+            index: entry.index,
+            num_hashes: entry.num_hashes,
+            hash: Hash::new(entry.hash),
+            executed_transaction_count: entry.executed_transaction_count,
+            starting_transaction_index: entry.starting_transaction_index,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct RecordedBlockMeta {
+    pub slot: Slot,
+    pub parent_slot: Slot,
+    pub parent_blockhash: String,
+    pub blockhash: String,
+    pub rewards: RewardsAndNumPartitions,
+    pub block_time: Option<UnixTimestamp>,
+    pub block_height: Option<u64>,
+    pub executed_transaction_count: u64,
+    pub entry_count: u64,
+}
+
+impl RecordedBlockMeta {
+    pub fn from_replica(block_meta: &ReplicaBlockInfoV4) -> Self {
+        Self {
+            slot: block_meta.slot,
+            parent_slot: block_meta.parent_slot,
+            parent_blockhash: block_meta.parent_blockhash.to_string(),
+            blockhash: block_meta.blockhash.to_string(),
+            rewards: block_meta.rewards.clone(),
+            block_time: block_meta.block_time,
+            block_height: block_meta.block_height,
+            executed_transaction_count: block_meta.executed_transaction_count,
+            entry_count: block_meta.entry_count,
+        }
+    }
+}
+
+// length prefixed bincode frame; also used to talk to out-of-process plugins
+pub fn write_frame<T: serde::Serialize>(writer: &mut impl Write, value: &T) -> bincode::Result<()> {
+    let frame = bincode::serialize(value)?;
+    writer.write_all(&(frame.len() as u32).to_le_bytes())?;
+    writer.write_all(&frame)?;
+    Ok(())
+}
+
+// None at the end of the stream; a truncated frame is treated as end
+pub fn read_frame<T: DeserializeOwned>(reader: &mut impl Read) -> bincode::Result<Option<T>> {
+    let mut len = [0u8; 4];
+    match reader.read_exact(&mut len) {
+        Ok(()) => {}
+        Err(err) if err.kind() == ErrorKind::UnexpectedEof => return Ok(None),
+        Err(err) => return Err(err.into()),
+    }
+    let mut frame = vec![0u8; u32::from_le_bytes(len) as usize];
+    match reader.read_exact(&mut frame) {
+        Ok(()) => {}
+        Err(err) if err.kind() == ErrorKind::UnexpectedEof => return Ok(None),
+        Err(err) => return Err(err.into()),
+    }
+    bincode::deserialize(&frame).map(Some)
+}
+
+// events are stored as length prefixed bincode frames
+#[derive(Debug)]
+pub struct RecordingWriter {
+    writer: BufWriter<File>,
+    started_at: Instant,
+}
+
+impl RecordingWriter {
+    pub fn create(path: &Path) -> std::io::Result<Self> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        writer.write_all(MAGIC)?;
+        Ok(Self {
+            writer,
+            started_at: Instant::now(),
+        })
+    }
+
+    pub fn write(&mut self, kind: RecordedKind) -> bincode::Result<()> {
+        let event = RecordedEvent {
+            elapsed_us: self.started_at.elapsed().as_micros() as u64,
+            kind,
+        };
+        write_frame(&mut self.writer, &event)
+    }
+
+    pub fn flush(&mut self) -> std::io::Result<()> {
+        self.writer.flush()
+    }
+}
+
+pub struct RecordingReader {
+    reader: BufReader<File>,
+}
+
+impl RecordingReader {
+    pub fn open(path: &Path) -> std::io::Result<Self> {
+        let mut reader = BufReader::new(File::open(path)?);
+        let mut magic = [0u8; 8];
+        reader.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            return Err(std::io::Error::new(
+                ErrorKind::InvalidData,
+                "not a geyser recording or unsupported version",
+            ));
+        }
+        Ok(Self { reader })
+    }
+
+    // None at the end of the recording; a truncated last frame (recorder killed) is treated as end
+    pub fn next_event(&mut self) -> bincode::Result<Option<RecordedEvent>> {
+        read_frame(&mut self.reader)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn write_and_read_recording() {
+        let path =
+            std::env::temp_dir().join(format!("geyser-recording-{}.bin", std::process::id()));
+
+        let account = RecordedAccount {
+            slot: 42,
+            pubkey: Pubkey::new_unique(),
+            lamports: 1000,
+            owner: Pubkey::new_unique(),
+            executable: false,
+            rent_epoch: u64::MAX,
+            data: vec![1, 2, 3],
+            write_version: 7,
+            is_startup: false,
+        };
+        let slot = RecordedSlot {
+            slot: 42,
+            parent: Some(41),
+            status: RecordedSlotStatus::Confirmed,
+        };
+
+        let mut writer = RecordingWriter::create(&path).unwrap();
+        writer
+            .write(RecordedKind::Account(account.clone()))
+            .unwrap();
+        writer.write(RecordedKind::Slot(slot.clone())).unwrap();
+        writer.flush().unwrap();
+        // simulate a recorder killed in the middle of a frame
+        std::fs::OpenOptions::new()
+            .append(true)
+            .open(&path)
+            .unwrap()
+            .write_all(&[100, 0, 0, 0, 1])
+            .unwrap();
+
+        let mut reader = RecordingReader::open(&path).unwrap();
+        let first = reader.next_event().unwrap().unwrap();
+        let second = reader.next_event().unwrap().unwrap();
+        assert!(first.elapsed_us <= second.elapsed_us);
+        assert!(matches!(first.kind, RecordedKind::Account(recorded) if recorded == account));
+        assert!(matches!(second.kind, RecordedKind::Slot(recorded) if recorded == slot));
+        assert!(reader.next_event().unwrap().is_none());
+
+        std::fs::remove_file(&path).unwrap();
+    }
+}
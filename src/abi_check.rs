@@ -0,0 +1,231 @@
+use crate::geyser_plugin_util::PluginLoadError;
+use cargo_lock::{Lockfile, Version};
+use log::{info, warn};
+use serde_derive::Serialize;
+use std::collections::BTreeSet;
+use std::path::Path;
+
+// versions the mock was built with, see build.rs
+const MOCK_PLUGIN_INTERFACE_VERSION: &str = env!("MOCK_PLUGIN_INTERFACE_VERSION");
+const MOCK_RUSTC_RELEASE: &str = env!("MOCK_RUSTC_RELEASE");
+const MOCK_RUSTC_COMMIT_HASH: &str = env!("MOCK_RUSTC_COMMIT_HASH");
+
+const PLUGIN_INTERFACE_CRATE: &str = "agave-geyser-plugin-interface";
+const RUSTC_COMMIT_HASH_LEN: usize = 40;
+
+// the GeyserPlugin trait object is passed across the library boundary without a stable ABI;
+// a plugin built with a different rustc or interface version usually segfaults
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum, Serialize)]
+pub enum AbiCheck {
+    // refuse to load the plugin
+    Strict,
+    // log a warning and load anyway
+    Warn,
+    Off,
+}
+
+// runs before the library is loaded, i.e. before any plugin code is executed
+pub fn check_plugin_abi(
+    abi_check: AbiCheck,
+    plugin: &str,
+    libpath: &Path,
+    lockfile: Option<&Lockfile>,
+) -> Result<(), PluginLoadError> {
+    if abi_check == AbiCheck::Off {
+        return Ok(());
+    }
+
+    let mut mismatches = vec![];
+    let mut checked = vec![];
+
+    if let Some(lockfile) = lockfile {
+        match plugin_interface_version(lockfile) {
+            Some(plugin_version) => {
+                checked.push(PLUGIN_INTERFACE_CRATE);
+                if let Some(mismatch) = interface_version_mismatch(&plugin_version) {
+                    mismatches.push(mismatch);
+                }
+            }
+            None => warn!(
+                "{} not found in Cargo.lock of plugin {}",
+                PLUGIN_INTERFACE_CRATE, plugin
+            ),
+        }
+    }
+
+    // std panic locations contain the commit hash of the compiler, e.g. /rustc/9b00956e5.../library/core/src/..
+    // missing if the library was stripped or built with remapped paths
+    // distro builds of rustc report "unknown" or nothing; every plugin would be a mismatch then
+    if is_commit_hash(MOCK_RUSTC_COMMIT_HASH) {
+        match std::fs::read(libpath) {
+            Ok(binary) => {
+                let commit_hashes = rustc_commit_hashes(&binary);
+                if !commit_hashes.is_empty() {
+                    checked.push("rustc");
+                    if !commit_hashes.contains(MOCK_RUSTC_COMMIT_HASH) {
+                        mismatches.push(format!(
+                            "built with rustc commit {} but the mock with rustc {} ({})",
+                            commit_hashes.into_iter().collect::<Vec<_>>().join(", "),
+                            MOCK_RUSTC_RELEASE,
+                            MOCK_RUSTC_COMMIT_HASH
+                        ));
+                    }
+                }
+            }
+            // reported properly when loading the library
+            Err(err) => warn!(
+                "cannot read {} for the ABI check: {}",
+                libpath.display(),
+                err
+            ),
+        }
+    } else {
+        info!(
+            "rustc of the mock has no commit hash ({:?}) - skipping the rustc check of plugin {}",
+            MOCK_RUSTC_COMMIT_HASH, plugin
+        );
+    }
+
+    if mismatches.is_empty() {
+        if checked.is_empty() {
+            info!(
+                "ABI of plugin {} could not be checked; add \"cargo_lock\" to its config",
+                plugin
+            );
+        } else {
+            info!(
+                "ABI of plugin {} matches the mock ({})",
+                plugin,
+                checked.join(", ")
+            );
+        }
+        return Ok(());
+    }
+
+    let msg = mismatches.join("; ");
+    match abi_check {
+        AbiCheck::Strict => Err(PluginLoadError::AbiMismatch {
+            plugin: plugin.to_string(),
+            msg,
+        }),
+        _ => {
+            warn!(
+                "plugin {} is probably not ABI compatible - expect segfaults: {}",
+                plugin, msg
+            );
+            Ok(())
+        }
+    }
+}
+
+pub fn plugin_interface_version(lockfile: &Lockfile) -> Option<Version> {
+    lockfile
+        .packages
+        .iter()
+        .find(|package| package.name.as_str() == PLUGIN_INTERFACE_CRATE)
+        .map(|package| package.version.clone())
+}
+
+// patch releases keep the trait as is
+fn interface_version_mismatch(plugin_version: &Version) -> Option<String> {
+    let mock_version = Version::parse(MOCK_PLUGIN_INTERFACE_VERSION).ok()?;
+    if (plugin_version.major, plugin_version.minor) == (mock_version.major, mock_version.minor) {
+        if plugin_version != &mock_version {
+            info!(
+                "plugin uses {} {} and the mock {}",
+                PLUGIN_INTERFACE_CRATE, plugin_version, mock_version
+            );
+        }
+        return None;
+    }
+    Some(format!(
+        "built with {} {} but the mock with {}",
+        PLUGIN_INTERFACE_CRATE, plugin_version, mock_version
+    ))
+}
+
+fn is_commit_hash(hash: &str) -> bool {
+    hash.len() == RUSTC_COMMIT_HASH_LEN && hash.bytes().all(|byte| byte.is_ascii_hexdigit())
+}
+
+fn rustc_commit_hashes(binary: &[u8]) -> BTreeSet<String> {
+    const PREFIX: &[u8] = b"/rustc/";
+
+    let mut commit_hashes = BTreeSet::new();
+    for position in 0..binary.len() {
+        // debug libraries are >100MB
+        if binary[position] != b'/' || !binary[position..].starts_with(PREFIX) {
+            continue;
+        }
+        let start = position + PREFIX.len();
+        if let Some(hash) = binary.get(start..start + RUSTC_COMMIT_HASH_LEN + 1) {
+            let (hash, separator) = hash.split_at(RUSTC_COMMIT_HASH_LEN);
+            if separator == b"/" && hash.iter().all(u8::is_ascii_hexdigit) {
+                commit_hashes.insert(String::from_utf8_lossy(hash).to_string());
+            }
+        }
+    }
+    commit_hashes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    #[test]
+    fn find_rustc_commit_hashes() {
+        let binary = b"\x00\x01/rustc/9b00956e56009bab2aa15d7bff10916599e3d6d6/library/core/src/fmt.rs\x00\
+            /rustc/9b00956e56009bab2aa15d7bff10916599e3d6d6/library/std/src/io.rs\x00/rustc/\x00/rustc/xyz/";
+        let commit_hashes = rustc_commit_hashes(binary);
+        assert_eq!(
+            commit_hashes.into_iter().collect::<Vec<_>>(),
+            vec!["9b00956e56009bab2aa15d7bff10916599e3d6d6"]
+        );
+        assert!(rustc_commit_hashes(b"no paths").is_empty());
+
+        assert!(is_commit_hash("9b00956e56009bab2aa15d7bff10916599e3d6d6"));
+        assert!(!is_commit_hash("unknown"));
+        assert!(!is_commit_hash(""));
+    }
+
+    #[test]
+    fn interface_versions() {
+        let lockfile = Lockfile::from_str(
+            r#"
+version = 3
+
+[[package]]
+name = "agave-geyser-plugin-interface"
+version = "1.18.26"
+"#,
+        )
+        .unwrap();
+        let plugin_version = plugin_interface_version(&lockfile).unwrap();
+        assert_eq!(plugin_version, Version::new(1, 18, 26));
+        assert!(interface_version_mismatch(&plugin_version).is_some());
+
+        let mock_version = Version::parse(MOCK_PLUGIN_INTERFACE_VERSION).unwrap();
+        let patched = Version::new(mock_version.major, mock_version.minor, 999);
+        assert!(interface_version_mismatch(&patched).is_none());
+    }
+
+    #[test]
+    fn strict_refuses_mismatch() {
+        let lockfile = Lockfile::from_str(
+            r#"
+version = 3
+
+[[package]]
+name = "agave-geyser-plugin-interface"
+version = "1.17.28"
+"#,
+        )
+        .unwrap();
+        let libpath = Path::new("/nonexistent/libplugin.so");
+        let err = check_plugin_abi(AbiCheck::Strict, "old", libpath, Some(&lockfile)).unwrap_err();
+        assert!(matches!(err, PluginLoadError::AbiMismatch { plugin, .. } if plugin == "old"));
+        assert!(check_plugin_abi(AbiCheck::Warn, "old", libpath, Some(&lockfile)).is_ok());
+        assert!(check_plugin_abi(AbiCheck::Off, "old", libpath, Some(&lockfile)).is_ok());
+    }
+}
@@ -0,0 +1,112 @@
+use rand::Rng;
+use solana_sdk::hash::hashv;
+use solana_sdk::pubkey::Pubkey;
+
+// universe of accounts receiving updates; pubkeys are derived from the index so that
+// millions of accounts do not need to be kept in memory
+#[derive(Debug)]
+pub struct AccountPool {
+    accounts_count: u64,
+    owners: Vec<Pubkey>,
+    pubkey_seed: [u8; 32],
+    // 0.0 is uniform, higher values concentrate the updates on few hot accounts
+    zipf_exponent: f64,
+}
+
+impl AccountPool {
+    pub fn new(
+        accounts_count: u64,
+        owners_count: u64,
+        zipf_exponent: f64,
+        rng: &mut impl Rng,
+    ) -> Self {
+        assert!(accounts_count > 0, "accounts_count must be positive");
+        assert!(owners_count > 0, "owners_count must be positive");
+        assert!(zipf_exponent >= 0.0, "zipf_exponent must not be negative");
+        Self {
+            accounts_count,
+            owners: (0..owners_count)
+                .map(|_| Pubkey::new_from_array(rng.gen()))
+                .collect(),
+            pubkey_seed: rng.gen(),
+            zipf_exponent,
+        }
+    }
+
+    pub fn pubkey(&self, index: u64) -> Pubkey {
+        Pubkey::new_from_array(hashv(&[&self.pubkey_seed, &index.to_le_bytes()]).to_bytes())
+    }
+
+    // stable for each account; the hot accounts are spread over all owners
+    pub fn owner(&self, index: u64) -> Pubkey {
+        self.owners[(index % self.owners.len() as u64) as usize]
+    }
+
+    // lower indexes are hotter
+    pub fn sample_index(&self, rng: &mut impl Rng) -> u64 {
+        if self.zipf_exponent == 0.0 {
+            return rng.gen_range(0, self.accounts_count);
+        }
+
+        // inverse CDF of the continuous power law x^-s on [1, n + 1)
+        let n = self.accounts_count as f64;
+        let s = self.zipf_exponent;
+        let u: f64 = rng.gen();
+        let x = if (s - 1.0).abs() < 1e-9 {
+            (n + 1.0).powf(u)
+        } else {
+            (((n + 1.0).powf(1.0 - s) - 1.0) * u + 1.0).powf(1.0 / (1.0 - s))
+        };
+        (x as u64).saturating_sub(1).min(self.accounts_count - 1)
+    }
+
+    // (pubkey, owner)
+    pub fn sample(&self, rng: &mut impl Rng) -> (Pubkey, Pubkey) {
+        let index = self.sample_index(rng);
+        (self.pubkey(index), self.owner(index))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    fn histogram(pool: &AccountPool, rng: &mut StdRng) -> Vec<u64> {
+        let mut counts = vec![0; pool.accounts_count as usize];
+        for _ in 0..100_000 {
+            counts[pool.sample_index(rng) as usize] += 1;
+        }
+        counts
+    }
+
+    #[test]
+    fn pubkeys_and_owners_are_stable() {
+        let mut rng = StdRng::seed_from_u64(42);
+        let pool = AccountPool::new(10_000_000, 3, 0.0, &mut rng);
+
+        assert_eq!(pool.pubkey(9_999_999), pool.pubkey(9_999_999));
+        assert_ne!(pool.pubkey(0), pool.pubkey(1));
+        assert_eq!(pool.owner(0), pool.owner(3));
+        assert_ne!(pool.owner(0), pool.owner(1));
+    }
+
+    #[test]
+    fn zipf_prefers_hot_accounts() {
+        let mut rng = StdRng::seed_from_u64(42);
+        let counts = histogram(&AccountPool::new(1000, 1, 1.2, &mut rng), &mut rng);
+
+        assert!(counts[0] > counts[1] && counts[1] > counts[10] && counts[10] > counts[999]);
+        // the 10 hottest accounts receive a large share of all updates
+        assert!(counts[0..10].iter().sum::<u64>() > 40_000);
+    }
+
+    #[test]
+    fn uniform_without_exponent() {
+        let mut rng = StdRng::seed_from_u64(42);
+        let counts = histogram(&AccountPool::new(10, 1, 0.0, &mut rng), &mut rng);
+
+        assert!(counts.iter().all(|count| (9_000..11_000).contains(count)));
+    }
+}
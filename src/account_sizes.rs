@@ -0,0 +1,157 @@
+use anyhow::{anyhow, bail, Context};
+use rand::distributions::{Distribution, WeightedIndex};
+use rand::Rng;
+use serde_derive::Deserialize;
+use std::path::Path;
+
+// (data size in bytes, weight)
+const MAINNET_2024: [(u64, u64); 4] = [(0, 1), (8, 2), (165, 4), (11099, 6)];
+// dominated by token accounts and mints
+const TOKEN_HEAVY: [(u64, u64); 5] = [(0, 5), (82, 5), (165, 80), (200, 5), (3762, 5)];
+// up to the max account size of 10MiB
+const LARGE_ACCOUNTS: [(u64, u64); 5] = [
+    (165, 20),
+    (11099, 30),
+    (200_000, 30),
+    (1_000_000, 15),
+    (10_485_760, 5),
+];
+
+pub const PRESETS: [&str; 3] = ["mainnet-2024", "token-heavy", "large-accounts"];
+
+#[derive(Debug, Deserialize, PartialEq)]
+struct SizeBucket {
+    size: u64,
+    weight: u64,
+}
+
+// histogram of account data sizes which is sampled randomly
+#[derive(Debug, Clone)]
+pub struct AccountSizeDistribution {
+    sizes: Vec<u64>,
+    index: WeightedIndex<u64>,
+}
+
+impl AccountSizeDistribution {
+    pub fn new(buckets: &[(u64, u64)]) -> anyhow::Result<Self> {
+        let index = WeightedIndex::new(buckets.iter().map(|(_, weight)| *weight))
+            .map_err(|err| anyhow!("invalid account size weights: {}", err))?;
+        Ok(Self {
+            sizes: buckets.iter().map(|(size, _)| *size).collect(),
+            index,
+        })
+    }
+
+    // all sizes with the same weight
+    pub fn uniform(sizes: &[u64]) -> anyhow::Result<Self> {
+        Self::new(&sizes.iter().map(|size| (*size, 1)).collect::<Vec<_>>())
+    }
+
+    pub fn preset(name: &str) -> Option<Self> {
+        let buckets: &[(u64, u64)] = match name {
+            "mainnet-2024" => &MAINNET_2024,
+            "token-heavy" => &TOKEN_HEAVY,
+            "large-accounts" => &LARGE_ACCOUNTS,
+            _ => return None,
+        };
+        Some(Self::new(buckets).unwrap())
+    }
+
+    // preset name or path to a .json ([{"size": 165, "weight": 4}, ...]) or .csv (size,weight) file
+    pub fn from_preset_or_file(preset_or_path: &str) -> anyhow::Result<Self> {
+        if let Some(distribution) = Self::preset(preset_or_path) {
+            return Ok(distribution);
+        }
+
+        let path = Path::new(preset_or_path);
+        if !path.exists() {
+            bail!(
+                "account size distribution must be one of {:?} or an existing file: {}",
+                PRESETS,
+                preset_or_path
+            );
+        }
+        let contents = std::fs::read_to_string(path)
+            .with_context(|| format!("failed to read {}", preset_or_path))?;
+
+        let buckets = match path.extension().and_then(|ext| ext.to_str()) {
+            Some("json") => parse_json(&contents)?,
+            Some("csv") => parse_csv(&contents)?,
+            _ => bail!("unsupported file type (json or csv): {}", preset_or_path),
+        };
+        Self::new(&buckets).with_context(|| format!("invalid histogram in {}", preset_or_path))
+    }
+
+    pub fn sample(&self, rng: &mut impl Rng) -> u64 {
+        self.sizes[self.index.sample(rng)]
+    }
+}
+
+fn parse_json(contents: &str) -> anyhow::Result<Vec<(u64, u64)>> {
+    let buckets: Vec<SizeBucket> = serde_json::from_str(contents)?;
+    Ok(buckets
+        .into_iter()
+        .map(|bucket| (bucket.size, bucket.weight))
+        .collect())
+}
+
+// one "size,weight" line per bucket; an optional header line is skipped
+fn parse_csv(contents: &str) -> anyhow::Result<Vec<(u64, u64)>> {
+    let mut buckets = vec![];
+    for (line_no, line) in contents.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') || (line_no == 0 && line.starts_with("size")) {
+            continue;
+        }
+        let (size, weight) = line
+            .split_once(',')
+            .ok_or_else(|| anyhow!("line {}: expected size,weight", line_no + 1))?;
+        buckets.push((
+            size.trim()
+                .parse()
+                .with_context(|| format!("line {}: invalid size", line_no + 1))?,
+            weight
+                .trim()
+                .parse()
+                .with_context(|| format!("line {}: invalid weight", line_no + 1))?,
+        ));
+    }
+    Ok(buckets)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    #[test]
+    fn presets_are_valid() {
+        for name in PRESETS {
+            assert!(AccountSizeDistribution::preset(name).is_some());
+        }
+        assert!(AccountSizeDistribution::preset("foo").is_none());
+    }
+
+    #[test]
+    fn parse_histogram_files() {
+        let json = r#"[{"size": 165, "weight": 3}, {"size": 0, "weight": 1}]"#;
+        assert_eq!(parse_json(json).unwrap(), vec![(165, 3), (0, 1)]);
+
+        let csv = "size,weight\n165,3\n\n0, 1\n";
+        assert_eq!(parse_csv(csv).unwrap(), vec![(165, 3), (0, 1)]);
+        assert!(parse_csv("165;3").is_err());
+    }
+
+    #[test]
+    fn sample_respects_weights() {
+        let mut rng = StdRng::seed_from_u64(42);
+        let distribution = AccountSizeDistribution::new(&[(165, 9), (11099, 1), (8, 0)]).unwrap();
+        let samples: Vec<u64> = (0..10_000).map(|_| distribution.sample(&mut rng)).collect();
+
+        let small = samples.iter().filter(|size| **size == 165).count();
+        assert!((8500..9500).contains(&small), "{}", small);
+        assert!(!samples.contains(&8));
+        assert!(AccountSizeDistribution::new(&[(165, 0)]).is_err());
+    }
+}
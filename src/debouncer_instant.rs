@@ -1,6 +1,5 @@
-use std::ops::Sub;
 use std::sync::atomic::{AtomicI64, Ordering};
-use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use std::time::{Duration, Instant};
 
 #[derive(Debug)]
 pub struct Debouncer {
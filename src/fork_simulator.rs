@@ -0,0 +1,224 @@
+use crate::geyser_plugin_util::MockSlot;
+use agave_geyser_plugin_interface::geyser_plugin_interface::SlotStatus;
+use log::info;
+use rand::Rng;
+use solana_program::clock::Slot;
+use std::collections::BTreeMap;
+
+const CONFIRMATION_DEPTH: usize = 2;
+const ROOT_DEPTH: usize = 32;
+
+// competing branch which will be abandoned (never confirmed nor rooted)
+#[derive(Debug)]
+struct Fork {
+    tip: Slot,
+    // slots still to be built on the fork
+    remaining: u64,
+    next_on_fork: bool,
+}
+
+// decides the parent of each new slot and derives confirmed/rooted slots from the main chain
+#[derive(Debug)]
+pub struct ForkSimulator {
+    fork_rate: f64,
+    fork_length: u64,
+    // recent slots of all branches mapped to their parent
+    slot_parents: BTreeMap<Slot, Slot>,
+    main_tip: Slot,
+    fork: Option<Fork>,
+    highest_confirmed: Slot,
+    highest_rooted: Slot,
+}
+
+impl ForkSimulator {
+    pub fn new(root: Slot, fork_rate: f64, fork_length: u64) -> Self {
+        assert!(
+            (0.0..=1.0).contains(&fork_rate),
+            "fork_rate must be in [0.0, 1.0]"
+        );
+        assert!(fork_length > 0, "fork_length must be positive");
+        Self {
+            fork_rate,
+            fork_length,
+            slot_parents: BTreeMap::new(),
+            main_tip: root,
+            fork: None,
+            highest_confirmed: root,
+            highest_rooted: root,
+        }
+    }
+
+    // returns the parent of the new slot
+    pub fn new_slot(&mut self, slot: Slot, rng: &mut impl Rng) -> Slot {
+        let parent = match self.fork.as_mut() {
+            Some(fork) if fork.next_on_fork => {
+                let parent = fork.tip;
+                fork.tip = slot;
+                fork.remaining -= 1;
+                fork.next_on_fork = false;
+                parent
+            }
+            Some(fork) => {
+                // leaders alternate between the branches
+                fork.next_on_fork = fork.remaining > 0;
+                if !fork.next_on_fork {
+                    info!("abandoning fork with tip {}", fork.tip);
+                    self.fork = None;
+                }
+                self.extend_main_chain(slot)
+            }
+            None if rng.gen_bool(self.fork_rate) => {
+                // competing block built on the same parent as the main tip
+                let parent = self
+                    .slot_parents
+                    .get(&self.main_tip)
+                    .copied()
+                    .unwrap_or(self.main_tip);
+                info!(
+                    "creating fork at slot {} with parent {} (main tip {})",
+                    slot, parent, self.main_tip
+                );
+                self.fork = Some(Fork {
+                    tip: slot,
+                    remaining: self.fork_length - 1,
+                    next_on_fork: false,
+                });
+                parent
+            }
+            None => self.extend_main_chain(slot),
+        };
+
+        self.slot_parents.insert(slot, parent);
+        parent
+    }
+
+    // the slots the next slot can be built on: the tips and, for a new fork, the parent of the main tip
+    pub fn possible_parents(&self) -> Vec<Slot> {
+        let mut parents = vec![self.main_tip];
+        parents.extend(self.slot_parents.get(&self.main_tip));
+        parents.extend(self.fork.as_ref().map(|fork| fork.tip));
+        parents
+    }
+
+    fn extend_main_chain(&mut self, slot: Slot) -> Slot {
+        let parent = self.main_tip;
+        self.main_tip = slot;
+        parent
+    }
+
+    // confirmed and rooted notifications for the main chain since the last call
+    pub fn commitment_updates(&mut self) -> Vec<MockSlot> {
+        // ascending, ending with the main tip
+        let mut main_chain = vec![self.main_tip];
+        while let Some(parent) = self.slot_parents.get(main_chain.last().unwrap()) {
+            main_chain.push(*parent);
+        }
+        main_chain.reverse();
+
+        let mut updates = vec![];
+
+        if let Some(confirmed) = main_chain.iter().rev().nth(CONFIRMATION_DEPTH).copied() {
+            for slot in main_chain
+                .iter()
+                .filter(|slot| **slot > self.highest_confirmed && **slot <= confirmed)
+            {
+                // agave does not pass the parent for confirmed slots
+                updates.push(MockSlot {
+                    slot: *slot,
+                    parent: None,
+                    status: SlotStatus::Confirmed,
+                });
+            }
+            self.highest_confirmed = self.highest_confirmed.max(confirmed);
+        }
+
+        if let Some(rooted) = main_chain.iter().rev().nth(ROOT_DEPTH).copied() {
+            for slot in main_chain
+                .iter()
+                .filter(|slot| **slot > self.highest_rooted && **slot <= rooted)
+            {
+                updates.push(MockSlot {
+                    slot: *slot,
+                    parent: self.slot_parents.get(slot).copied(),
+                    status: SlotStatus::Rooted,
+                });
+            }
+            self.highest_rooted = self.highest_rooted.max(rooted);
+            // everything below the root is either final or abandoned
+            self.slot_parents = self.slot_parents.split_off(&self.highest_rooted);
+        }
+
+        updates
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    #[test]
+    fn linear_chain() {
+        let mut rng = StdRng::seed_from_u64(42);
+        let mut simulator = ForkSimulator::new(99, 0.0, 1);
+        for slot in 100..200 {
+            assert_eq!(simulator.new_slot(slot, &mut rng), slot - 1);
+        }
+
+        let updates = simulator.commitment_updates();
+        let confirmed: Vec<Slot> = updates
+            .iter()
+            .filter(|update| update.status == SlotStatus::Confirmed)
+            .map(|update| update.slot)
+            .collect();
+        let rooted: Vec<Slot> = updates
+            .iter()
+            .filter(|update| update.status == SlotStatus::Rooted)
+            .map(|update| update.slot)
+            .collect();
+        assert_eq!(confirmed, (100..=197).collect::<Vec<_>>());
+        assert_eq!(rooted, (100..=167).collect::<Vec<_>>());
+        assert!(simulator.commitment_updates().is_empty());
+    }
+
+    #[test]
+    fn forks_are_never_rooted() {
+        let mut rng = StdRng::seed_from_u64(42);
+        let mut simulator = ForkSimulator::new(99, 0.2, 3);
+        let mut abandoned = vec![];
+        let mut updates = vec![];
+        for slot in 100..1000 {
+            simulator.new_slot(slot, &mut rng);
+            if let Some(fork) = simulator.fork.as_ref() {
+                if fork.tip == slot {
+                    abandoned.push(slot);
+                }
+            }
+            updates.extend(simulator.commitment_updates());
+        }
+
+        assert!(!abandoned.is_empty());
+        for update in updates {
+            assert!(!abandoned.contains(&update.slot));
+        }
+    }
+
+    #[test]
+    fn parents_are_predicted() {
+        let mut rng = StdRng::seed_from_u64(42);
+        let mut simulator = ForkSimulator::new(99, 0.3, 4);
+        for slot in 100..2000 {
+            let possible_parents = simulator.possible_parents();
+            let parent = simulator.new_slot(slot, &mut rng);
+            assert!(
+                possible_parents.contains(&parent),
+                "parent {} of slot {} not in {:?}",
+                parent,
+                slot,
+                possible_parents
+            );
+            simulator.commitment_updates();
+        }
+    }
+}
@@ -1,29 +1,234 @@
+use crate::abi_check::{check_plugin_abi, plugin_interface_version, AbiCheck};
+use crate::plugin_dispatcher::Callback;
+use crate::plugin_host::spawn_plugin_host;
 use agave_geyser_plugin_interface::geyser_plugin_interface::{
-    GeyserPlugin, GeyserPluginError, ReplicaAccountInfoV3, SlotStatus,
+    GeyserPlugin, GeyserPluginError, ReplicaAccountInfoV3, ReplicaAccountInfoVersions,
+    ReplicaBlockInfoV4, ReplicaBlockInfoVersions, ReplicaEntryInfoV2, ReplicaEntryInfoVersions,
+    ReplicaTransactionInfoV2, ReplicaTransactionInfoVersions, Result as PluginResult, SlotStatus,
 };
+use cargo_lock::Lockfile;
 use libloading::Library;
-use log::info;
-use solana_geyser_plugin_manager::geyser_plugin_manager::{
-    GeyserPluginManagerError, LoadedGeyserPlugin,
-};
-use solana_program::clock::{Epoch, Slot};
+use log::{info, warn};
+use serde_derive::{Deserialize, Serialize};
+use solana_geyser_plugin_manager::geyser_plugin_manager::LoadedGeyserPlugin;
+use solana_program::clock::{Epoch, Slot, UnixTimestamp};
 use solana_program::pubkey::Pubkey;
 use solana_sdk::account::{AccountSharedData, ReadableAccount};
-use solana_sdk::commitment_config::CommitmentLevel;
+use solana_sdk::hash::Hash;
+use solana_sdk::signature::Signature;
 use solana_sdk::transaction::SanitizedTransaction;
-use std::path::Path;
-use std::sync::Arc;
+use solana_transaction_status::{Reward, RewardsAndNumPartitions, TransactionStatusMeta};
+use std::path::{Path, PathBuf};
 
 #[derive(Debug)]
 pub enum MockMessage {
     Slot(MockSlot),
     Account(MockAccount),
+    Transaction(Box<MockTransaction>),
+    Entry(MockEntry),
+    BlockMeta(MockBlockMeta),
+    EndOfStartup,
+}
+
+impl MockMessage {
+    pub fn callback(&self) -> Callback {
+        match self {
+            MockMessage::Slot(_) => Callback::UpdateSlotStatus,
+            MockMessage::Account(_) => Callback::UpdateAccount,
+            MockMessage::Transaction(_) => Callback::NotifyTransaction,
+            MockMessage::Entry(_) => Callback::NotifyEntry,
+            MockMessage::BlockMeta(_) => Callback::NotifyBlockMetadata,
+            MockMessage::EndOfStartup => Callback::NotifyEndOfStartup,
+        }
+    }
+
+    // see kind()
+    pub const KINDS: [&'static str; 6] = [
+        "slot",
+        "account",
+        "transaction",
+        "entry",
+        "block_meta",
+        "end_of_startup",
+    ];
+
+    pub fn kind(&self) -> &'static str {
+        match self {
+            MockMessage::Slot(_) => "slot",
+            MockMessage::Account(_) => "account",
+            MockMessage::Transaction(_) => "transaction",
+            MockMessage::Entry(_) => "entry",
+            MockMessage::BlockMeta(_) => "block_meta",
+            MockMessage::EndOfStartup => "end_of_startup",
+        }
+    }
+
+    // approximate memory held by the message while it is queued, including the heap of transactions
+    // and rewards; allocator overhead is not counted
+    pub fn payload_bytes(&self) -> usize {
+        std::mem::size_of::<MockMessage>()
+            + match self {
+                MockMessage::Account(mock_account) => mock_account.data.len(),
+                MockMessage::Transaction(mock_transaction) => {
+                    std::mem::size_of::<MockTransaction>()
+                        + transaction_heap_bytes(mock_transaction)
+                }
+                MockMessage::BlockMeta(mock_block_meta) => {
+                    mock_block_meta.parent_blockhash.len()
+                        + mock_block_meta.blockhash.len()
+                        + rewards_bytes(&mock_block_meta.rewards.rewards)
+                }
+                MockMessage::Slot(_) | MockMessage::Entry(_) | MockMessage::EndOfStartup => 0,
+            }
+    }
+
+    pub fn account_data_len(&self) -> Option<usize> {
+        match self {
+            MockMessage::Account(mock_account) => Some(mock_account.data.len()),
+            _ => None,
+        }
+    }
+}
+
+// the vectors of the sanitized message and of the status meta
+fn transaction_heap_bytes(mock_transaction: &MockTransaction) -> usize {
+    let message = mock_transaction.transaction.message();
+    let meta = &mock_transaction.transaction_status_meta;
+    let instructions: usize = message
+        .instructions()
+        .iter()
+        .map(|instruction| {
+            std::mem::size_of_val(instruction) + instruction.accounts.len() + instruction.data.len()
+        })
+        .sum();
+    let inner_instructions: usize = meta
+        .inner_instructions
+        .iter()
+        .flatten()
+        .flat_map(|inner_instructions| &inner_instructions.instructions)
+        .map(|inner| {
+            std::mem::size_of_val(inner)
+                + inner.instruction.accounts.len()
+                + inner.instruction.data.len()
+        })
+        .sum();
+    let token_balances: usize = meta
+        .pre_token_balances
+        .iter()
+        .chain(&meta.post_token_balances)
+        .flatten()
+        .map(|balance| {
+            std::mem::size_of_val(balance)
+                + balance.mint.len()
+                + balance.owner.len()
+                + balance.program_id.len()
+                + balance.ui_token_amount.amount.len()
+                + balance.ui_token_amount.ui_amount_string.len()
+        })
+        .sum();
+    let log_messages: usize = meta
+        .log_messages
+        .iter()
+        .flatten()
+        .map(|log_message| std::mem::size_of::<String>() + log_message.len())
+        .sum();
+
+    std::mem::size_of_val(mock_transaction.transaction.signatures())
+        // plus the is_writable cache of the message
+        + message.account_keys().len() * (std::mem::size_of::<Pubkey>() + 1)
+        + instructions
+        + std::mem::size_of_val(&meta.pre_balances[..])
+        + std::mem::size_of_val(&meta.post_balances[..])
+        + inner_instructions
+        + token_balances
+        + log_messages
+        + meta.rewards.as_deref().map_or(0, rewards_bytes)
+        + std::mem::size_of_val(&meta.loaded_addresses.writable[..])
+        + std::mem::size_of_val(&meta.loaded_addresses.readonly[..])
+        + meta
+            .return_data
+            .as_ref()
+            .map_or(0, |return_data| return_data.data.len())
+}
+
+fn rewards_bytes(rewards: &[Reward]) -> usize {
+    rewards
+        .iter()
+        .map(|reward| std::mem::size_of::<Reward>() + reward.pubkey.len())
+        .sum()
+}
+
+// calls the plugin with the latest replica versions like agave 2.0
+pub fn notify_plugin(plugin: &dyn GeyserPlugin, message: &MockMessage) -> PluginResult<()> {
+    match message {
+        MockMessage::Account(mock_account) => {
+            let account_v3 = ReplicaAccountInfoV3 {
+                pubkey: mock_account.pubkey.as_ref(),
+                lamports: mock_account.lamports,
+                owner: mock_account.owner.as_ref(),
+                executable: mock_account.executable,
+                rent_epoch: mock_account.rent_epoch,
+                data: mock_account.data.as_ref(),
+                write_version: mock_account.write_version,
+                txn: None,
+            };
+            plugin.update_account(
+                ReplicaAccountInfoVersions::V0_0_3(&account_v3),
+                mock_account.slot,
+                mock_account.is_startup,
+            )
+        }
+        MockMessage::EndOfStartup => plugin.notify_end_of_startup(),
+        MockMessage::Transaction(mock_transaction) => {
+            let transaction_v2 = ReplicaTransactionInfoV2 {
+                signature: &mock_transaction.signature,
+                is_vote: mock_transaction.is_vote,
+                transaction: &mock_transaction.transaction,
+                transaction_status_meta: &mock_transaction.transaction_status_meta,
+                index: mock_transaction.index,
+            };
+            plugin.notify_transaction(
+                ReplicaTransactionInfoVersions::V0_0_2(&transaction_v2),
+                mock_transaction.slot,
+            )
+        }
+        MockMessage::Entry(mock_entry) => {
+            let entry_v2 = ReplicaEntryInfoV2 {
+                slot: mock_entry.slot,
+                index: mock_entry.index,
+                num_hashes: mock_entry.num_hashes,
+                hash: mock_entry.hash.as_ref(),
+                executed_transaction_count: mock_entry.executed_transaction_count,
+                starting_transaction_index: mock_entry.starting_transaction_index,
+            };
+            plugin.notify_entry(ReplicaEntryInfoVersions::V0_0_2(&entry_v2))
+        }
+        MockMessage::Slot(mock_slot) => {
+            plugin.update_slot_status(mock_slot.slot, mock_slot.parent, mock_slot.status)
+        }
+        MockMessage::BlockMeta(mock_block_meta) => {
+            let block_meta = ReplicaBlockInfoV4 {
+                parent_slot: mock_block_meta.parent_slot,
+                slot: mock_block_meta.slot,
+                parent_blockhash: &mock_block_meta.parent_blockhash,
+                blockhash: &mock_block_meta.blockhash,
+                rewards: &mock_block_meta.rewards,
+                block_time: mock_block_meta.block_time,
+                block_height: mock_block_meta.block_height,
+                executed_transaction_count: mock_block_meta.executed_transaction_count,
+                entry_count: mock_block_meta.entry_count,
+            };
+            plugin.notify_block_metadata(ReplicaBlockInfoVersions::V0_0_4(&block_meta))
+        }
+    }
 }
 
 #[derive(Debug)]
 pub struct MockSlot {
     pub slot: Slot,
-    pub commitment_level: CommitmentLevel,
+    // set for Processed and Rooted like in agave; None for Confirmed
+    pub parent: Option<Slot>,
+    pub status: SlotStatus,
 }
 
 #[derive(Debug)]
@@ -35,97 +240,223 @@ pub struct MockAccount {
     pub owner: Pubkey,
     pub executable: bool,
     pub rent_epoch: Epoch,
+    pub write_version: u64,
+    // account is part of the startup snapshot
+    pub is_startup: bool,
 }
 
-// see also GeyserPluginManager: load_plugin
+#[derive(Debug)]
+pub struct MockTransaction {
+    pub slot: Slot,
+    pub signature: Signature,
+    pub is_vote: bool,
+    pub transaction: SanitizedTransaction,
+    pub transaction_status_meta: TransactionStatusMeta,
+    // index in the block
+    pub index: usize,
+}
+
+#[derive(Debug)]
+pub struct MockBlockMeta {
+    pub slot: Slot,
+    pub parent_slot: Slot,
+    // base58
+    pub parent_blockhash: String,
+    pub blockhash: String,
+    pub rewards: RewardsAndNumPartitions,
+    pub block_time: Option<UnixTimestamp>,
+    pub block_height: Option<u64>,
+    pub executed_transaction_count: u64,
+    pub entry_count: u64,
+}
 
-pub fn setup_plugin(config_file: &Path) -> Result<Arc<LoadedGeyserPlugin>, GeyserPluginError> {
-    let (mut new_plugin, new_lib, new_config_file) = load_plugin_from_config(config_file).unwrap();
+#[derive(Debug)]
+pub struct MockEntry {
+    pub slot: Slot,
+    // index in the block
+    pub index: usize,
+    pub num_hashes: u64,
+    pub hash: Hash,
+    pub executed_transaction_count: u64,
+    pub starting_transaction_index: usize,
+}
 
-    setup_logger_for_plugin(new_plugin.as_ref())?;
+// exit codes are stable so that CI can tell the failures apart
+// serializable to pass it from the plugin host process
+#[derive(Debug, thiserror::Error, Serialize, Deserialize)]
+pub enum PluginLoadError {
+    #[error("cannot read plugin config file {config_file}: {msg}")]
+    ConfigUnreadable { config_file: String, msg: String },
+    #[error("plugin config file {config_file} is not valid json5: {msg}")]
+    ConfigInvalid { config_file: String, msg: String },
+    #[error("libpath is not set in plugin config file {config_file}")]
+    LibPathMissing { config_file: String },
+    #[error("cannot load library {libpath} of plugin {plugin}: {msg}")]
+    LibraryLoadFailed {
+        plugin: String,
+        libpath: String,
+        msg: String,
+    },
+    #[error("symbol _create_plugin missing in library {libpath} of plugin {plugin}: {msg}")]
+    SymbolMissing {
+        plugin: String,
+        libpath: String,
+        msg: String,
+    },
+    #[error("plugin {plugin} failed to load: {msg}")]
+    OnLoadFailed { plugin: String, msg: String },
+    // raised by the ABI preflight check
+    #[error("plugin {plugin} was built for a different ABI: {msg}")]
+    AbiMismatch { plugin: String, msg: String },
+    #[error("plugin host process of {plugin} failed: {msg}")]
+    PluginHostFailed { plugin: String, msg: String },
+}
 
-    // Attempt to on_load with new plugin
-    match new_plugin.on_load(config_file.as_os_str().to_str().unwrap(), true) {
-        // On success, push plugin and library
-        Ok(()) => {
-            info!("Successfully loaded plugin: {}", new_plugin.name());
-            // self.plugins.push(new_plugin);
-            // self.libs.push(new_lib);
+impl PluginLoadError {
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            PluginLoadError::ConfigUnreadable { .. } => 10,
+            PluginLoadError::ConfigInvalid { .. } => 11,
+            PluginLoadError::LibPathMissing { .. } => 12,
+            PluginLoadError::LibraryLoadFailed { .. } => 13,
+            PluginLoadError::SymbolMissing { .. } => 14,
+            PluginLoadError::OnLoadFailed { .. } => 15,
+            PluginLoadError::AbiMismatch { .. } => 16,
+            PluginLoadError::PluginHostFailed { .. } => 17,
         }
+    }
+}
 
-        // On failure, return error
-        Err(err) => {
-            // return Err(jsonrpc_core::error::Error {
-            //     code: ErrorCode::InvalidRequest,
-            //     message: format!(
-            //         "Failed to start new plugin (previous plugin was dropped!): {err}"
-            //     ),
-            //     data: None,
-            // });
-        }
+#[derive(Debug, Clone, Copy)]
+pub struct LoadOptions {
+    pub abi_check: AbiCheck,
+    // run each plugin in a child process so that crashes do not take down the mock
+    pub isolate: bool,
+}
+
+// see also GeyserPluginManager: load_plugin and reload_plugin (is_reload=true)
+// the library must outlive the plugin as the plugin code lives in it; there is none for isolated plugins
+pub fn setup_plugin(
+    config_file: &Path,
+    is_reload: bool,
+    options: LoadOptions,
+) -> Result<(LoadedGeyserPlugin, Option<Library>), PluginLoadError> {
+    if options.isolate {
+        let plugin = spawn_plugin_host(config_file, is_reload, options.abi_check)?;
+        info!("Successfully loaded isolated plugin: {}", plugin.name());
+        return Ok((LoadedGeyserPlugin::new(Box::new(plugin), None), None));
+    }
+
+    let (new_plugin, new_lib, new_config_file) =
+        load_plugin_from_config(config_file, options.abi_check)?;
+    initialize_plugin(new_plugin, Some(new_lib), new_config_file, is_reload)
+}
+
+// sets up the logger and calls on_load
+fn initialize_plugin(
+    mut plugin: LoadedGeyserPlugin,
+    lib: Option<Library>,
+    config_file: &str,
+    is_reload: bool,
+) -> Result<(LoadedGeyserPlugin, Option<Library>), PluginLoadError> {
+    let loaded = setup_logger_for_plugin(plugin.as_ref())
+        .map_err(|err| format!("setup_logger: {}", err))
+        // never call into a plugin which failed to initialize
+        .and_then(|()| {
+            plugin
+                .on_load(config_file, is_reload)
+                .map_err(|err| err.to_string())
+        });
+    if let Err(msg) = loaded {
+        let name = plugin.name().to_string();
+        // the plugin code lives in the library
+        drop(plugin);
+        drop(lib);
+        return Err(PluginLoadError::OnLoadFailed { plugin: name, msg });
     }
+    info!("Successfully loaded plugin: {}", plugin.name());
 
-    Ok(Arc::new(new_plugin))
+    Ok((plugin, lib))
 }
 
 fn load_plugin_from_config(
     geyser_plugin_config_file: &Path,
-) -> Result<(LoadedGeyserPlugin, Library, &str), GeyserPluginManagerError> {
-    use std::{fs::File, io::Read, path::PathBuf};
+    abi_check: AbiCheck,
+) -> Result<(LoadedGeyserPlugin, Library, &str), PluginLoadError> {
     type PluginConstructor = unsafe fn() -> *mut dyn GeyserPlugin;
     use libloading::Symbol;
 
-    let mut file = match File::open(geyser_plugin_config_file) {
-        Ok(file) => file,
-        Err(err) => {
-            return Err(GeyserPluginManagerError::CannotOpenConfigFile(format!(
-                "Failed to open the plugin config file {geyser_plugin_config_file:?}, error: {err:?}"
-            )));
-        }
-    };
+    let config_file = geyser_plugin_config_file.to_string_lossy().to_string();
 
-    let mut contents = String::new();
-    if let Err(err) = file.read_to_string(&mut contents) {
-        return Err(GeyserPluginManagerError::CannotReadConfigFile(format!(
-            "Failed to read the plugin config file {geyser_plugin_config_file:?}, error: {err:?}"
-        )));
-    }
-
-    let result: serde_json::Value = match json5::from_str(&contents) {
-        Ok(value) => value,
-        Err(err) => {
-            return Err(GeyserPluginManagerError::InvalidConfigFileFormat(format!(
-                "The config file {geyser_plugin_config_file:?} is not in a valid Json5 format, error: {err:?}"
-            )));
+    let contents = std::fs::read_to_string(geyser_plugin_config_file).map_err(|err| {
+        PluginLoadError::ConfigUnreadable {
+            config_file: config_file.clone(),
+            msg: err.to_string(),
         }
-    };
+    })?;
+
+    let result: serde_json::Value =
+        json5::from_str(&contents).map_err(|err| PluginLoadError::ConfigInvalid {
+            config_file: config_file.clone(),
+            msg: err.to_string(),
+        })?;
 
     let libpath = result["libpath"]
         .as_str()
-        .ok_or(GeyserPluginManagerError::LibPathNotSet)?;
-    let mut libpath = PathBuf::from(libpath);
-    if libpath.is_relative() {
-        let config_dir = geyser_plugin_config_file.parent().ok_or_else(|| {
-            GeyserPluginManagerError::CannotOpenConfigFile(format!(
-                "Failed to resolve parent of {geyser_plugin_config_file:?}",
-            ))
+        .ok_or_else(|| PluginLoadError::LibPathMissing {
+            config_file: config_file.clone(),
         })?;
-        libpath = config_dir.join(libpath);
-    }
+    let libpath = resolve_config_path(geyser_plugin_config_file, libpath);
 
     let plugin_name = result["name"].as_str().map(|s| s.to_owned());
+    // the plugin itself is not known yet
+    let plugin_label = plugin_name.clone().unwrap_or_else(|| config_file.clone());
+
+    // optional, the Cargo.lock the plugin was built with; only needed for the ABI check
+    let lockfile = match result["cargo_lock"].as_str() {
+        Some(cargo_lock) if abi_check != AbiCheck::Off => {
+            match Lockfile::load(resolve_config_path(geyser_plugin_config_file, cargo_lock)) {
+                Ok(lockfile) => Some(lockfile),
+                Err(err) if abi_check == AbiCheck::Strict => {
+                    return Err(PluginLoadError::ConfigInvalid {
+                        config_file: config_file.clone(),
+                        msg: format!("cargo_lock: {}", err),
+                    })
+                }
+                Err(err) => {
+                    warn!(
+                        "cannot read cargo_lock of plugin {}: {} - skipping the interface version check",
+                        plugin_label, err
+                    );
+                    None
+                }
+            }
+        }
+        _ => None,
+    };
+    check_plugin_abi(abi_check, &plugin_label, &libpath, lockfile.as_ref())?;
 
     let config_file = geyser_plugin_config_file
         .as_os_str()
         .to_str()
-        .ok_or(GeyserPluginManagerError::InvalidPluginPath)?;
+        .ok_or_else(|| PluginLoadError::ConfigUnreadable {
+            config_file: config_file.clone(),
+            msg: "path is not valid utf-8".to_string(),
+        })?;
 
     let (plugin, lib) = unsafe {
-        let lib = Library::new(libpath)
-            .map_err(|e| GeyserPluginManagerError::PluginLoadError(e.to_string()))?;
-        let constructor: Symbol<PluginConstructor> = lib
-            .get(b"_create_plugin")
-            .map_err(|e| GeyserPluginManagerError::PluginLoadError(e.to_string()))?;
+        let lib = Library::new(&libpath).map_err(|err| PluginLoadError::LibraryLoadFailed {
+            plugin: plugin_label.clone(),
+            libpath: libpath.display().to_string(),
+            msg: err.to_string(),
+        })?;
+        let constructor: Symbol<PluginConstructor> =
+            lib.get(b"_create_plugin")
+                .map_err(|err| PluginLoadError::SymbolMissing {
+                    plugin: plugin_label.clone(),
+                    libpath: libpath.display().to_string(),
+                    msg: err.to_string(),
+                })?;
         let plugin_raw = constructor();
         (Box::from_raw(plugin_raw), lib)
     };
@@ -136,6 +467,50 @@ fn load_plugin_from_config(
     ))
 }
 
+// the plugin interface has no version; taken from the Cargo.lock in the config (see "cargo_lock")
+#[derive(Debug, Default, Clone, Serialize)]
+pub struct PluginBuildInfo {
+    pub version: Option<String>,
+    pub interface_version: Option<String>,
+}
+
+// best effort for reports, the plugin is loaded already
+pub fn plugin_build_info(config_file: &Path) -> PluginBuildInfo {
+    let lockfile_and_libpath = || {
+        let contents = std::fs::read_to_string(config_file).ok()?;
+        let config: serde_json::Value = json5::from_str(&contents).ok()?;
+        let libpath = resolve_config_path(config_file, config["libpath"].as_str()?);
+        let cargo_lock = resolve_config_path(config_file, config["cargo_lock"].as_str()?);
+        Some((Lockfile::load(cargo_lock).ok()?, libpath))
+    };
+    let Some((lockfile, libpath)) = lockfile_and_libpath() else {
+        return PluginBuildInfo::default();
+    };
+    // libyellowstone_grpc_geyser.so is built from the package yellowstone-grpc-geyser
+    let package_name = libpath
+        .file_stem()
+        .and_then(|stem| stem.to_str())
+        .map(|stem| stem.strip_prefix("lib").unwrap_or(stem).replace('_', "-"));
+    PluginBuildInfo {
+        version: lockfile
+            .packages
+            .iter()
+            .find(|package| Some(package.name.as_str().replace('_', "-")) == package_name)
+            .map(|package| package.version.to_string()),
+        interface_version: plugin_interface_version(&lockfile).map(|version| version.to_string()),
+    }
+}
+
+// relative paths in the config are relative to the config file like in the validator
+fn resolve_config_path(config_file: &Path, path: &str) -> PathBuf {
+    let path = PathBuf::from(path);
+    match config_file.parent() {
+        Some(config_dir) if path.is_relative() => config_dir.join(path),
+        _ => path,
+    }
+}
+
+#[allow(dead_code)]
 pub fn accountinfo_from_shared_account_data<'a>(
     account: &'a AccountSharedData,
     txn: &'a Option<&'a SanitizedTransaction>,
@@ -158,10 +533,149 @@ fn setup_logger_for_plugin(new_plugin: &dyn GeyserPlugin) -> Result<(), GeyserPl
     new_plugin.setup_logger(log::logger(), log::max_level())
 }
 
-pub fn slot_status_from_commitment_level(level: CommitmentLevel) -> SlotStatus {
-    match level {
-        CommitmentLevel::Processed => SlotStatus::Processed,
-        CommitmentLevel::Confirmed => SlotStatus::Confirmed,
-        CommitmentLevel::Finalized => SlotStatus::Rooted,
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    fn load_error(name: &str, config: Option<&str>) -> PluginLoadError {
+        let config_file =
+            std::env::temp_dir().join(format!("geyser-mock-{}-{}.json", name, std::process::id()));
+        if let Some(config) = config {
+            std::fs::write(&config_file, config).unwrap();
+        }
+        let options = LoadOptions {
+            abi_check: AbiCheck::Strict,
+            isolate: false,
+        };
+        let err = setup_plugin(&config_file, false, options).err().unwrap();
+        let _ = std::fs::remove_file(&config_file);
+        err
+    }
+
+    #[test]
+    fn cargo_lock_is_only_required_by_strict() {
+        let config_file =
+            std::env::temp_dir().join(format!("geyser-mock-nolock-{}.json", std::process::id()));
+        std::fs::write(
+            &config_file,
+            r#"{ "libpath": "/nonexistent/libfoo.so", "cargo_lock": "/nonexistent/Cargo.lock" }"#,
+        )
+        .unwrap();
+        let load = |abi_check| {
+            let options = LoadOptions {
+                abi_check,
+                isolate: false,
+            };
+            setup_plugin(&config_file, false, options).err().unwrap()
+        };
+        assert!(matches!(
+            load(AbiCheck::Strict),
+            PluginLoadError::ConfigInvalid { .. }
+        ));
+        assert!(matches!(
+            load(AbiCheck::Warn),
+            PluginLoadError::LibraryLoadFailed { .. }
+        ));
+        assert!(matches!(
+            load(AbiCheck::Off),
+            PluginLoadError::LibraryLoadFailed { .. }
+        ));
+        let _ = std::fs::remove_file(&config_file);
+    }
+
+    // any shared library without _create_plugin
+    fn libc_path() -> String {
+        std::fs::read_to_string("/proc/self/maps")
+            .unwrap()
+            .lines()
+            .filter_map(|line| line.split_whitespace().nth(5))
+            .find(|path| path.contains("/libc.so") || path.contains("/libc-"))
+            .expect("libc is mapped")
+            .to_string()
+    }
+
+    #[derive(Debug)]
+    struct FailingOnLoad;
+
+    impl GeyserPlugin for FailingOnLoad {
+        fn name(&self) -> &'static str {
+            "failing_on_load"
+        }
+
+        fn on_load(&mut self, _config_file: &str, _is_reload: bool) -> PluginResult<()> {
+            Err(GeyserPluginError::ConfigFileReadError {
+                msg: "bad config".to_string(),
+            })
+        }
+    }
+
+    #[test]
+    fn load_errors_are_distinct() {
+        let cargo_lock =
+            std::env::temp_dir().join(format!("geyser-mock-abi-{}.lock", std::process::id()));
+        std::fs::write(
+            &cargo_lock,
+            r#"
+version = 3
+
+[[package]]
+name = "agave-geyser-plugin-interface"
+version = "1.17.28"
+"#,
+        )
+        .unwrap();
+        let abi_mismatch = load_error(
+            "abi",
+            Some(&format!(
+                r#"{{ "libpath": "/nonexistent/libfoo.so", "cargo_lock": "{}" }}"#,
+                cargo_lock.display()
+            )),
+        );
+        let _ = std::fs::remove_file(&cargo_lock);
+
+        let on_load_failed = initialize_plugin(
+            LoadedGeyserPlugin::new(Box::new(FailingOnLoad), None),
+            None,
+            "config.json",
+            false,
+        )
+        .err()
+        .unwrap();
+
+        let errors = [
+            load_error("missing", None),
+            load_error("invalid", Some("{ libpath")),
+            load_error("nolibpath", Some(r#"{ "name": "foo" }"#)),
+            load_error("nolib", Some(r#"{ "libpath": "/nonexistent/libfoo.so" }"#)),
+            // trailing comma, the config is json5
+            load_error(
+                "nosymbol",
+                Some(&format!(r#"{{ "libpath": "{}", }}"#, libc_path())),
+            ),
+            on_load_failed,
+            abi_mismatch,
+            PluginLoadError::PluginHostFailed {
+                plugin: "foo".to_string(),
+                msg: "exited before connecting".to_string(),
+            },
+        ];
+        assert!(matches!(
+            errors[0],
+            PluginLoadError::ConfigUnreadable { .. }
+        ));
+        assert!(matches!(errors[1], PluginLoadError::ConfigInvalid { .. }));
+        assert!(matches!(errors[2], PluginLoadError::LibPathMissing { .. }));
+        assert!(
+            matches!(&errors[3], PluginLoadError::LibraryLoadFailed { plugin, .. } if plugin.ends_with(".json"))
+        );
+        assert!(matches!(errors[4], PluginLoadError::SymbolMissing { .. }));
+        assert!(
+            matches!(&errors[5], PluginLoadError::OnLoadFailed { plugin, msg } if plugin == "failing_on_load" && msg.contains("bad config"))
+        );
+        assert!(matches!(errors[6], PluginLoadError::AbiMismatch { .. }));
+
+        let exit_codes: HashSet<i32> = errors.iter().map(|err| err.exit_code()).collect();
+        assert_eq!(exit_codes.len(), errors.len());
     }
 }
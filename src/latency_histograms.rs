@@ -0,0 +1,140 @@
+use crate::plugin_dispatcher::Callback;
+use hdrhistogram::Histogram;
+use std::time::Duration;
+
+// upper bounds (exclusive) of the account data size buckets of update_account
+const ACCOUNT_SIZE_BUCKETS: [(usize, &str); 4] = [
+    (256, "<256B"),
+    (1024, "<1KiB"),
+    (16 * 1024, "<16KiB"),
+    (usize::MAX, ">=16KiB"),
+];
+
+// 3 significant digits keep the error below 0.1% at any scale
+const SIGNIFICANT_DIGITS: u8 = 3;
+
+pub const PERCENTILES: [(f64, &str); 4] =
+    [(0.5, "p50"), (0.9, "p90"), (0.99, "p99"), (0.999, "p99.9")];
+
+#[derive(Debug, Clone)]
+pub struct LatencySummary {
+    pub callback: Callback,
+    // only for update_account; None is the histogram over all sizes
+    pub account_size: Option<&'static str>,
+    pub count: u64,
+    // same order as PERCENTILES
+    pub percentiles: [Duration; PERCENTILES.len()],
+    pub max: Duration,
+}
+
+// call latencies in nanoseconds; the histograms are created on the first call
+#[derive(Debug, Default)]
+pub struct LatencyHistograms {
+    // indexed by Callback
+    by_callback: [Option<Histogram<u64>>; Callback::ALL.len()],
+    // update_account only, indexed like ACCOUNT_SIZE_BUCKETS
+    by_account_size: [Option<Histogram<u64>>; ACCOUNT_SIZE_BUCKETS.len()],
+}
+
+impl LatencyHistograms {
+    pub fn record(&mut self, callback: Callback, account_size: Option<usize>, elapsed: Duration) {
+        let nanos = elapsed.as_nanos().min(u64::MAX as u128) as u64;
+        record_nanos(&mut self.by_callback[callback as usize], nanos);
+        if let Some(account_size) = account_size {
+            let bucket = ACCOUNT_SIZE_BUCKETS
+                .iter()
+                .position(|(upper_bound, _)| account_size < *upper_bound)
+                .unwrap_or(ACCOUNT_SIZE_BUCKETS.len() - 1);
+            record_nanos(&mut self.by_account_size[bucket], nanos);
+        }
+    }
+
+    // per callback in Callback::ALL order, update_account followed by its size buckets
+    pub fn summaries(&self) -> Vec<LatencySummary> {
+        let mut summaries = vec![];
+        for callback in Callback::ALL {
+            let Some(histogram) = &self.by_callback[callback as usize] else {
+                continue;
+            };
+            summaries.push(summarize(callback, None, histogram));
+            if callback == Callback::UpdateAccount {
+                for ((_, label), histogram) in
+                    ACCOUNT_SIZE_BUCKETS.iter().zip(self.by_account_size.iter())
+                {
+                    if let Some(histogram) = histogram {
+                        summaries.push(summarize(callback, Some(label), histogram));
+                    }
+                }
+            }
+        }
+        summaries
+    }
+}
+
+fn record_nanos(histogram: &mut Option<Histogram<u64>>, nanos: u64) {
+    histogram
+        .get_or_insert_with(|| Histogram::new(SIGNIFICANT_DIGITS).unwrap())
+        .saturating_record(nanos);
+}
+
+fn summarize(
+    callback: Callback,
+    account_size: Option<&'static str>,
+    histogram: &Histogram<u64>,
+) -> LatencySummary {
+    LatencySummary {
+        callback,
+        account_size,
+        count: histogram.len(),
+        percentiles: PERCENTILES
+            .map(|(quantile, _)| Duration::from_nanos(histogram.value_at_quantile(quantile))),
+        max: Duration::from_nanos(histogram.max()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn percentiles_by_callback_and_account_size() {
+        let mut histograms = LatencyHistograms::default();
+        for micros in 1..=1000 {
+            histograms.record(
+                Callback::UpdateAccount,
+                Some(165),
+                Duration::from_micros(micros),
+            );
+        }
+        histograms.record(
+            Callback::UpdateAccount,
+            Some(20_000),
+            Duration::from_millis(50),
+        );
+        histograms.record(Callback::UpdateSlotStatus, None, Duration::from_micros(3));
+
+        let summaries = histograms.summaries();
+        let labels: Vec<_> = summaries
+            .iter()
+            .map(|summary| (summary.callback, summary.account_size, summary.count))
+            .collect();
+        assert_eq!(
+            labels,
+            vec![
+                (Callback::UpdateAccount, None, 1001),
+                (Callback::UpdateAccount, Some("<256B"), 1000),
+                (Callback::UpdateAccount, Some(">=16KiB"), 1),
+                (Callback::UpdateSlotStatus, None, 1),
+            ]
+        );
+
+        let small_accounts = &summaries[1];
+        let p50 = small_accounts.percentiles[0].as_micros();
+        assert!((499..=501).contains(&p50), "p50 was {}us", p50);
+        let p99 = small_accounts.percentiles[2].as_micros();
+        assert!((989..=991).contains(&p99), "p99 was {}us", p99);
+        // the single slow large account only shows up in the max of all accounts
+        assert!(summaries[0].max >= Duration::from_millis(49));
+        assert!(small_accounts.max <= Duration::from_millis(2));
+    }
+}
@@ -1,42 +1,149 @@
-use crate::geyser_plugin_util::{
-    accountinfo_from_shared_account_data, setup_plugin, slot_status_from_commitment_level,
-    MockAccount, MockMessage,
-};
-use agave_geyser_plugin_interface::geyser_plugin_interface::{
-    ReplicaAccountInfoV3, ReplicaAccountInfoVersions, ReplicaBlockInfoV3, ReplicaBlockInfoV4,
-    ReplicaBlockInfoVersions, SlotStatus,
-};
+use crate::abi_check::AbiCheck;
+use crate::account_sizes::AccountSizeDistribution;
+use crate::geyser_plugin_util::{notify_plugin, setup_plugin, LoadOptions, MockMessage};
+use crate::message_queue::{message_queue, Backpressure, MessageReceiver};
+use crate::pacing::Pacing;
+use crate::plugin_dispatcher::{ErrorPolicy, PluginDispatcher};
+use crate::run_report::{DispatchStats, ReloadFailure, RunReport};
 use clap::Parser;
-use log::{info, warn};
-use solana_program::pubkey::Pubkey;
-use solana_sdk::account::{AccountSharedData, ReadableAccount};
-use solana_sdk::clock::Slot;
-use solana_sdk::commitment_config::CommitmentLevel;
-use solana_transaction_status::RewardsAndNumPartitions;
+use geyser_recorder::recording::RecordingReader;
+use libloading::Library;
+use log::{error, info, warn};
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use serde_derive::Serialize;
+use solana_geyser_plugin_manager::geyser_plugin_manager::LoadedGeyserPlugin;
+use std::net::SocketAddr;
 use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use tokio::signal::unix::{signal, SignalKind};
 use tracing::debug;
 use tracing_subscriber::EnvFilter;
 
+mod abi_check;
+mod account_pool;
+mod account_sizes;
 mod debouncer_instant;
+mod fork_simulator;
 mod geyser_plugin_util;
+mod latency_histograms;
+mod message_queue;
+mod metrics;
 mod mock_service;
+mod pacing;
+mod plugin_dispatcher;
+mod plugin_host;
+mod replay;
+mod run_report;
+mod skip_simulator;
 
-#[derive(Parser, Debug)]
+#[derive(Parser, Debug, Clone, Serialize)]
 #[command(author, version, about, long_about = None)]
 pub struct Args {
-    // point to config.json
+    // point to config.json; multiple plugins are called in the given order
+    #[arg(long, required = true, num_args = 1..)]
+    pub geyser_plugin_config: Vec<String>,
+    // compare rustc and agave-geyser-plugin-interface versions of the plugins (see "cargo_lock" in the config) before loading them
+    #[arg(long, value_enum, default_value = "warn")]
+    pub abi_check: AbiCheck,
+    // what to do if a plugin callback fails or panics: abort, log (like the validator) or count:<max errors> (abort above)
+    #[arg(long, default_value = "abort")]
+    pub plugin_error_policy: ErrorPolicy,
+    // run each plugin in a child process which is restarted if the plugin crashes
     #[arg(long)]
-    pub geyser_plugin_config: String,
+    pub isolate_plugins: bool,
+    // internal: serve the plugin of --geyser-plugin-config over this unix socket (see --isolate-plugins)
+    #[arg(long, hide = true)]
+    pub plugin_host: Option<String>,
     #[arg(long, default_value = "30000000")]
     pub account_bytes_per_slot: u64,
+    // preset (mainnet-2024, token-heavy, large-accounts) or .json/.csv histogram file
+    #[arg(long, default_value = "mainnet-2024")]
+    pub account_size_distribution: String,
+    // number of distinct accounts receiving updates
+    #[arg(long, default_value = "100")]
+    pub account_pool_size: u64,
+    // owner programs of the account pool
+    #[arg(long, default_value = "1")]
+    pub account_owners: u64,
+    // 0.0 picks accounts uniformly, around 1.0 a few hot accounts get most updates
+    #[arg(long, default_value = "0.0")]
+    pub account_zipf_exponent: f64,
     #[arg(long, default_value = "0.0")]
     pub compressibility: f64,
+    // ms over which the account updates are spread within a 400ms slot
     #[arg(long, default_value = "350.0")]
     pub slot_tick_delay: f64,
+    // realtime, a speed multiplier like 10x or max (unthrottled)
+    #[arg(long, default_value = "realtime")]
+    pub pacing: Pacing,
+    // what the generated traffic does if the channel to the plugins is full; slot status updates are never dropped
+    #[arg(long, value_enum, default_value = "drop-newest")]
+    pub backpressure: Backpressure,
+    // budget of the channel to the plugins in bytes of queued messages (mostly account data)
+    #[arg(long, default_value = "268435456")]
+    pub channel_max_bytes: usize,
+    #[arg(long, default_value = "0")]
+    pub transactions_per_slot: u64,
+    // fraction of transactions which are votes
+    #[arg(long, default_value = "0.75")]
+    pub vote_transaction_ratio: f64,
+    #[arg(long, default_value = "0")]
+    pub entries_per_slot: u64,
+    // accounts sent with is_startup=true before the regular traffic starts
+    #[arg(long, default_value = "0")]
+    pub startup_accounts: u64,
+    // comma separated, defaults to --account-size-distribution
+    #[arg(long, value_delimiter = ',')]
+    pub startup_account_sizes: Vec<u64>,
+    #[arg(long, default_value = "10")]
+    pub startup_owners: u64,
+    // probability per slot to start a competing fork which gets abandoned
+    #[arg(long, default_value = "0.0")]
+    pub fork_rate: f64,
+    // number of slots built on an abandoned fork
+    #[arg(long, default_value = "2")]
+    pub fork_length: u64,
+    // probability that a slot is skipped
+    #[arg(long, default_value = "0.0")]
+    pub skip_rate: f64,
+    // probability that a leader skips all 4 slots of its window
+    #[arg(long, default_value = "0.0")]
+    pub leader_skip_rate: f64,
+    // the first one is the fee reward of the leader
+    #[arg(long, default_value = "1")]
+    pub rewards_per_block: u64,
+    // num_partitions of the block rewards (partitioned epoch rewards)
+    #[arg(long)]
+    pub reward_partitions: Option<u64>,
+    // drives all randomness; runs with the same seed produce identical plugin calls
+    #[arg(long)]
+    pub seed: Option<u64>,
+    // replay a file written by the geyser-recorder plugin instead of generating traffic
+    #[arg(long)]
+    pub replay: Option<String>,
+    // serve prometheus metrics on http://<addr>/metrics; must not clash with the prometheus port of the plugin
+    #[arg(long)]
+    pub metrics_addr: Option<SocketAddr>,
+    // write a summary of the run to this json file at exit
+    #[arg(long)]
+    pub report_json: Option<String>,
+    // same as --report-json as key,value lines
+    #[arg(long)]
+    pub report_csv: Option<String>,
 }
 
-// note: if this channel fills the process will very likely die with OOM at some point!
-const MOCK_BUFFER: usize = 102400;
+// see --plugin-error-policy
+const PLUGIN_ERROR_EXIT_CODE: i32 = 20;
+// see --backpressure fail-run
+const BACKPRESSURE_EXIT_CODE: i32 = 21;
+// the --replay recording could not be read to the end
+const REPLAY_EXIT_CODE: i32 = 22;
+// like an uncaught panic of the main thread
+const TRAFFIC_PANIC_EXIT_CODE: i32 = 101;
+
+const TIMINGS_LOG_INTERVAL: std::time::Duration = std::time::Duration::from_secs(10);
 
 #[tokio::main]
 async fn main() {
@@ -45,103 +152,411 @@ async fn main() {
         .init();
 
     let args = Args::parse();
+    let run_started_at = chrono::Utc::now();
+
+    if let Some(socket_path) = &args.plugin_host {
+        let exit_code = plugin_host::run_plugin_host(
+            Path::new(socket_path),
+            Path::new(&args.geyser_plugin_config[0]),
+            args.abi_check,
+        );
+        std::process::exit(exit_code);
+    }
 
     assert!(
         args.compressibility >= 0.0 && args.compressibility <= 1.0,
         "compressibility must be in [0.0, 1.0]"
     );
+    assert!(
+        args.vote_transaction_ratio >= 0.0 && args.vote_transaction_ratio <= 1.0,
+        "vote_transaction_ratio must be in [0.0, 1.0]"
+    );
+    assert!(args.slot_tick_delay >= 0.0, "slot_tick_delay must be >= 0");
+    // checked again by the traffic task, but only after the plugins were loaded
+    assert!(
+        args.account_pool_size > 0,
+        "account_pool_size must be positive"
+    );
+    assert!(args.account_owners > 0, "account_owners must be positive");
+    assert!(
+        args.account_zipf_exponent >= 0.0,
+        "account_zipf_exponent must not be negative"
+    );
+    assert!(
+        args.fork_rate >= 0.0 && args.fork_rate <= 1.0,
+        "fork_rate must be in [0.0, 1.0]"
+    );
+    assert!(args.fork_length > 0, "fork_length must be positive");
+    assert!(
+        args.skip_rate >= 0.0 && args.skip_rate <= 1.0,
+        "skip_rate must be in [0.0, 1.0]"
+    );
+    assert!(
+        args.leader_skip_rate >= 0.0 && args.leader_skip_rate <= 1.0,
+        "leader_skip_rate must be in [0.0, 1.0]"
+    );
 
+    let account_sizes =
+        AccountSizeDistribution::from_preset_or_file(&args.account_size_distribution).unwrap();
+    let startup_account_sizes = if args.startup_account_sizes.is_empty() {
+        account_sizes.clone()
+    } else {
+        AccountSizeDistribution::uniform(&args.startup_account_sizes).unwrap()
+    };
+
+    // before loading the plugins, a typo in the path must not cost a plugin startup
+    let recording = match &args.replay {
+        Some(recording_file) => match RecordingReader::open(Path::new(recording_file)) {
+            Ok(reader) => Some(reader),
+            Err(err) => {
+                error!("cannot open recording {}: {}", recording_file, err);
+                std::process::exit(1);
+            }
+        },
+        None => None,
+    };
+
+    let load_options = LoadOptions {
+        abi_check: args.abi_check,
+        isolate: args.isolate_plugins,
+    };
+    let mut plugins: Vec<(LoadedGeyserPlugin, Option<Library>)> = vec![];
+    for config in &args.geyser_plugin_config {
+        info!("Loading geyser plugin from config: {}", config);
+        let (plugin, lib) = match setup_plugin(Path::new(config), false, load_options) {
+            Ok(loaded) => loaded,
+            Err(err) => {
+                error!("{}", err);
+                // the plugins loaded so far might hold resources
+                for (mut plugin, lib) in plugins {
+                    plugin.on_unload();
+                    drop(plugin);
+                    drop(lib);
+                }
+                std::process::exit(err.exit_code());
+            }
+        };
+        plugins.push((plugin, lib));
+    }
+    let mut dispatcher = PluginDispatcher::new(plugins, args.plugin_error_policy);
+    // fixed for the run like the notifiers of the validator; a reloaded plugin cannot enable more streams
+    let notifications_enabled = dispatcher.notifications_enabled();
     info!(
-        "Loading geyser plugin from config: {}",
-        args.geyser_plugin_config
+        "Active streams: {}",
+        notifications_enabled.active_streams().join(", ")
     );
-    let config_file = Path::new(&args.geyser_plugin_config);
-    assert!(config_file.exists(), "Config file must exist");
 
-    let plugin = setup_plugin(config_file.as_ref()).unwrap();
+    if let Some(metrics_addr) = args.metrics_addr {
+        match metrics::serve(&metrics_addr) {
+            Ok(server) => {
+                info!("Serving metrics on http://{}/metrics", metrics_addr);
+                tokio::task::spawn(server);
+            }
+            Err(err) => {
+                error!("cannot serve metrics on {}: {}", metrics_addr, err);
+                dispatcher.unload();
+                std::process::exit(1);
+            }
+        }
+    }
 
-    let (channel_tx, mut channel_rx) = tokio::sync::mpsc::channel::<MockMessage>(MOCK_BUFFER);
+    let (channel_tx, channel_rx) = message_queue(args.channel_max_bytes, args.backpressure);
+    metrics::channel_max_bytes(args.channel_max_bytes);
 
     // tokio::task::spawn(yellowstone_mock_service::helloworld_traffic(channel_tx));
-    tokio::task::spawn(mock_service::mainnet_traffic(
-        channel_tx,
-        args.account_bytes_per_slot,
-        args.compressibility,
-        args.slot_tick_delay,
-    ));
-
-    std::thread::spawn(move || {
+    let mut rng = match args.seed {
+        Some(seed) => {
+            info!("Using seed {} for traffic generation", seed);
+            StdRng::seed_from_u64(seed)
+        }
+        None => StdRng::from_entropy(),
+    };
+
+    let replay_failed = Arc::new(AtomicBool::new(false));
+    let replay_failure = replay_failed.clone();
+    let traffic_args = args.clone();
+    let traffic_task = tokio::task::spawn(async move {
+        if let (Some(recording_file), Some(reader)) = (traffic_args.replay, recording) {
+            let replayed =
+                replay::replay_recording(channel_tx, recording_file, reader, traffic_args.pacing)
+                    .await;
+            if let Err(err) = replayed {
+                // the sender is gone, the messages read so far are still dispatched
+                error!("failed to read recording - stopping replay: {}", err);
+                replay_failure.store(true, Ordering::Relaxed);
+            }
+            return;
+        }
+
+        let startup_completed = mock_service::startup_snapshot(
+            &channel_tx,
+            &mut rng,
+            // the snapshot accounts go through the account notifier as well
+            if notifications_enabled.accounts {
+                traffic_args.startup_accounts
+            } else {
+                0
+            },
+            &startup_account_sizes,
+            traffic_args.startup_owners,
+            traffic_args.compressibility,
+        )
+        .await;
+        if !startup_completed {
+            return;
+        }
+
+        mock_service::mainnet_traffic(
+            channel_tx,
+            mock_service::MainnetTrafficConfig {
+                bytes_per_slot: traffic_args.account_bytes_per_slot,
+                account_sizes,
+                account_pool_size: traffic_args.account_pool_size,
+                account_owners: traffic_args.account_owners,
+                account_zipf_exponent: traffic_args.account_zipf_exponent,
+                compressibility: traffic_args.compressibility,
+                // --slot-tick-delay is in milliseconds
+                slot_tick_delay: std::time::Duration::from_secs_f64(
+                    traffic_args.slot_tick_delay / 1000.0,
+                ),
+                pacing: traffic_args.pacing,
+                transactions_per_slot: traffic_args.transactions_per_slot,
+                vote_transaction_ratio: traffic_args.vote_transaction_ratio,
+                entries_per_slot: traffic_args.entries_per_slot,
+                fork_rate: traffic_args.fork_rate,
+                fork_length: traffic_args.fork_length,
+                skip_rate: traffic_args.skip_rate,
+                leader_skip_rate: traffic_args.leader_skip_rate,
+                rewards_per_block: traffic_args.rewards_per_block,
+                reward_partitions: traffic_args.reward_partitions,
+                wall_clock: traffic_args.seed.is_none(),
+                notifications_enabled,
+            },
+            rng,
+        )
+        .await;
+    });
+
+    let traffic_abort = traffic_task.abort_handle();
+    tokio::task::spawn(async move {
+        shutdown_signal().await;
+        // dropping the sender lets the receiver drain the channel and shut down
+        info!("shutdown requested - stopping traffic and draining the channel");
+        traffic_abort.abort();
+
+        shutdown_signal().await;
+        warn!("second shutdown signal - exiting without draining");
+        std::process::exit(1);
+    });
+
+    // SIGHUP reloads all plugins like the validator admin rpc reloadPlugin
+    let reload_requested = Arc::new(AtomicBool::new(false));
+    let reload_signal = reload_requested.clone();
+    tokio::task::spawn(async move {
+        let mut sighup = signal(SignalKind::hangup()).unwrap();
+        while sighup.recv().await.is_some() {
+            info!("plugin reload requested");
+            reload_signal.store(true, Ordering::Relaxed);
+        }
+    });
+    let plugin_configs = args.geyser_plugin_config.clone();
+    let error_policy = args.plugin_error_policy;
+
+    let (dispatcher, aborted, stats) = std::thread::spawn(move || {
         let log_debouncer = debouncer_instant::Debouncer::new(std::time::Duration::from_millis(10));
+        let started_at = std::time::Instant::now();
+        let timings_debouncer = debouncer_instant::Debouncer::new(TIMINGS_LOG_INTERVAL);
+        let mut aborted = false;
+        let mut stats = DispatchStats::default();
 
         'recv_loop: loop {
-            match channel_rx.blocking_recv() {
-                Some(MockMessage::Account(mock_account)) => {
-                    // usually there are some 10-50 messages in the channel
-                    if channel_rx.len() > 100 && log_debouncer.can_fire() {
+            let Some(message) = channel_rx.blocking_recv() else {
+                warn!("channel closed - shutting down");
+                break 'recv_loop;
+            };
+
+            let enabled = match &message {
+                // only seen on replay, the generated traffic skips them
+                MockMessage::Account(_) => notifications_enabled.accounts,
+                MockMessage::Transaction(_) => notifications_enabled.transactions,
+                MockMessage::Entry(_) => notifications_enabled.entries,
+                _ => true,
+            };
+            if enabled {
+                match &message {
+                    MockMessage::Account(mock_account) => {
+                        // usually there are some 10-50 messages in the channel
+                        if channel_rx.len() > 100 && log_debouncer.can_fire() {
+                            info!(
+                                "sending account {:?} with data_len={} ({} messags in channel)",
+                                mock_account.pubkey,
+                                mock_account.data.len(),
+                                channel_rx.len()
+                            );
+                        }
+                    }
+                    MockMessage::EndOfStartup => {
                         info!(
-                            "sending account {:?} with data_len={} ({} messags in channel)",
-                            mock_account.pubkey,
-                            mock_account.data.len(),
-                            channel_rx.len()
+                            "notify end of startup after {:.3}s",
+                            started_at.elapsed().as_secs_f64()
                         );
                     }
-
-                    let account_v3 = ReplicaAccountInfoV3 {
-                        pubkey: mock_account.pubkey.as_ref(),
-                        lamports: mock_account.lamports,
-                        owner: mock_account.owner.as_ref(),
-                        executable: mock_account.executable,
-                        rent_epoch: mock_account.rent_epoch,
-                        data: mock_account.data.as_ref(),
-                        write_version: 999999,
-                        txn: None,
-                    };
-
-                    let account = ReplicaAccountInfoVersions::V0_0_3(&account_v3);
-                    plugin
-                        .update_account(account, mock_account.slot, false)
-                        .unwrap();
-                }
-                Some(MockMessage::Slot(mock_slot)) => {
-                    debug!(
-                        "updating slot to {} with commitment {}",
-                        mock_slot.slot, mock_slot.commitment_level
-                    );
-                    plugin
-                        .update_slot_status(
+                    MockMessage::Slot(mock_slot) => {
+                        debug!(
+                            "updating slot to {} (parent {:?}) with status {}",
                             mock_slot.slot,
-                            None,
-                            slot_status_from_commitment_level(mock_slot.commitment_level),
-                        )
-                        .unwrap();
-
-                    if mock_slot.commitment_level == CommitmentLevel::Processed {
-                        let block_meta = ReplicaBlockInfoV4 {
-                            parent_slot: mock_slot.slot - 1,
-                            slot: mock_slot.slot,
-                            parent_blockhash: "nohash",
-                            blockhash: "nohash",
-                            rewards: &RewardsAndNumPartitions {
-                                rewards: vec![],
-                                num_partitions: None,
-                            },
-                            block_time: None,
-                            block_height: None,
-                            executed_transaction_count: 0,
-                            entry_count: 0,
-                        };
-                        plugin
-                            .notify_block_metadata(ReplicaBlockInfoVersions::V0_0_4(&block_meta))
-                            .unwrap();
+                            mock_slot.parent,
+                            mock_slot.status.as_str()
+                        );
                     }
+                    _ => {}
                 }
-                None => {
-                    warn!("channel closed - shutting down");
+                let dispatched =
+                    dispatcher.dispatch(message.callback(), message.account_data_len(), |plugin| {
+                        notify_plugin(plugin.as_ref(), &message)
+                    });
+                metrics::message_dispatched(&message);
+                stats.record(&message, channel_rx.len(), channel_rx.bytes_in_flight());
+                if let Err(err) = dispatched {
+                    // dropping the receiver stops the traffic
+                    error!("{} - aborting the run", err);
+                    aborted = true;
                     break 'recv_loop;
                 }
             }
+
+            if reload_requested.swap(false, Ordering::Relaxed) {
+                let failures =
+                    reload_plugins(&mut dispatcher, &plugin_configs, load_options, &channel_rx);
+                let failed = !failures.is_empty();
+                stats.reload_failures.extend(failures);
+                // like a failing callback, only the log policy keeps running without the plugin
+                if failed && error_policy != ErrorPolicy::Log {
+                    error!("plugin reload failed - aborting the run");
+                    break 'recv_loop;
+                }
+            }
+
+            if timings_debouncer.can_fire() {
+                dispatcher.log_timings();
+                dispatcher.log_latencies();
+                dispatcher.log_errors();
+            }
         }
+
+        dispatcher.log_timings();
+        dispatcher.log_latencies();
+        dispatcher.log_errors();
+        stats.overflowed = channel_rx.overflowed();
+        // the traffic task is done once the channel was closed
+        stats.replay_failed = replay_failed.load(Ordering::Relaxed);
+        (dispatcher, aborted, stats)
     })
     .join()
     .unwrap();
+
+    // a panic which already happened is still reported after the abort
+    traffic_task.abort();
+    let traffic_panicked = match traffic_task.await {
+        Err(err) if err.is_panic() => {
+            error!("traffic generation panicked - the run is incomplete");
+            true
+        }
+        _ => false,
+    };
+
+    let report = RunReport::new(
+        &args,
+        run_started_at.to_rfc3339(),
+        (chrono::Utc::now() - run_started_at)
+            .to_std()
+            .unwrap_or_default(),
+        aborted,
+        &dispatcher,
+        &stats,
+    );
+    write_report(&report, &args);
+
+    dispatcher.unload();
+    if traffic_panicked {
+        std::process::exit(TRAFFIC_PANIC_EXIT_CODE);
+    }
+    if aborted {
+        std::process::exit(PLUGIN_ERROR_EXIT_CODE);
+    }
+    if let Some(failure) = stats.reload_failures.first() {
+        if args.plugin_error_policy != ErrorPolicy::Log {
+            std::process::exit(failure.exit_code);
+        }
+    }
+    if stats.overflowed {
+        error!("the traffic was stopped as the channel was full (--backpressure fail-run)");
+        std::process::exit(BACKPRESSURE_EXIT_CODE);
+    }
+    if stats.replay_failed {
+        error!("the replay was stopped as the recording could not be read to the end");
+        std::process::exit(REPLAY_EXIT_CODE);
+    }
+    info!("shutdown complete");
+}
+
+// dispatch is paused while the plugins are reloaded one after another; the traffic keeps flowing into the channel;
+// returns the plugins which stay unloaded
+fn reload_plugins(
+    dispatcher: &mut PluginDispatcher,
+    plugin_configs: &[String],
+    load_options: LoadOptions,
+    channel_rx: &MessageReceiver,
+) -> Vec<ReloadFailure> {
+    let paused_at = std::time::Instant::now();
+    let pushed_before = channel_rx.pushed();
+    let mut failures = vec![];
+    for (index, config) in plugin_configs.iter().enumerate() {
+        info!("Reloading geyser plugin from config: {}", config);
+        if let Err(err) = dispatcher.reload_plugin(index, Path::new(config), load_options) {
+            error!("{} - plugin stays unloaded", err);
+            failures.push(ReloadFailure {
+                config_file: config.clone(),
+                error: err.to_string(),
+                exit_code: err.exit_code(),
+            });
+        }
+    }
+    let bytes_after = channel_rx.bytes_in_flight();
+    // counts the messages evicted by --backpressure drop-oldest as well
+    info!(
+        "plugin reload paused dispatch for {:.3}ms; {} messages pushed meanwhile ({} in channel, {} bytes)",
+        paused_at.elapsed().as_secs_f64() * 1000.0,
+        channel_rx.pushed() - pushed_before,
+        channel_rx.len(),
+        bytes_after
+    );
+    // the next large account would not fit anymore
+    if bytes_after >= channel_rx.max_bytes() * 9 / 10 {
+        warn!("channel was full during reload - see --backpressure");
+    }
+    failures
+}
+
+fn write_report(report: &RunReport, args: &Args) {
+    if let Some(path) = &args.report_json {
+        match report.write_json(Path::new(path)) {
+            Ok(()) => info!("run report written to {}", path),
+            Err(err) => error!("cannot write run report to {}: {}", path, err),
+        }
+    }
+    if let Some(path) = &args.report_csv {
+        match report.write_csv(Path::new(path)) {
+            Ok(()) => info!("run report written to {}", path),
+            Err(err) => error!("cannot write run report to {}: {}", path, err),
+        }
+    }
+}
+
+async fn shutdown_signal() {
+    let mut sigterm = signal(SignalKind::terminate()).unwrap();
+    tokio::select! {
+        _ = tokio::signal::ctrl_c() => {}
+        _ = sigterm.recv() => {}
+    }
 }
@@ -0,0 +1,417 @@
+use crate::geyser_plugin_util::MockMessage;
+use crate::metrics;
+use serde_derive::Serialize;
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Condvar, Mutex, MutexGuard};
+use tokio::sync::Notify;
+
+// what the traffic generator does if the plugins do not keep up and the queue is full
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum, Serialize)]
+pub enum Backpressure {
+    // drop the message which does not fit
+    DropNewest,
+    // drop the oldest queued message to make room
+    DropOldest,
+    // wait for room like the replay stage of the validator waits for the geyser notifiers
+    BlockProducer,
+    // stop the traffic and exit with an error
+    FailRun,
+}
+
+#[derive(Debug)]
+pub enum Sent {
+    Queued,
+    // the messages which were dropped instead, the new one or the oldest ones
+    Dropped(Vec<MockMessage>),
+}
+
+#[derive(Debug, PartialEq, Eq, thiserror::Error)]
+pub enum SendError {
+    #[error("queue was closed")]
+    Closed,
+    #[error("queue is full")]
+    Full,
+}
+
+struct State {
+    messages: VecDeque<MockMessage>,
+    // MockMessage::payload_bytes of the queued messages
+    bytes: usize,
+    sender_alive: bool,
+    receiver_alive: bool,
+}
+
+struct Shared {
+    state: Mutex<State>,
+    max_bytes: usize,
+    // wakes the dispatcher thread
+    not_empty: Condvar,
+    // wakes the traffic task; a notification without waiter is kept for the next wait
+    not_full: Notify,
+    overflowed: AtomicBool,
+    // messages queued since the start, including the ones evicted later
+    pushed: AtomicU64,
+}
+
+impl Shared {
+    fn lock(&self) -> MutexGuard<'_, State> {
+        self.state.lock().unwrap()
+    }
+
+    // a message larger than the budget still gets through an empty queue
+    fn has_room(&self, state: &State, message: &MockMessage) -> bool {
+        state.messages.is_empty() || state.bytes + message.payload_bytes() <= self.max_bytes
+    }
+
+    // on every change, so the gauge also moves while the dispatcher is stuck in a plugin call
+    fn update_metrics(&self, state: &State) {
+        metrics::channel_filled(state.messages.len(), state.bytes);
+    }
+}
+
+// bounded by the payload bytes of the queued messages, not their number, as accounts range
+// from 0 to 10MiB; single producer (traffic task), single consumer (dispatcher thread)
+pub struct MessageSender {
+    shared: Arc<Shared>,
+    backpressure: Backpressure,
+}
+
+pub struct MessageReceiver {
+    shared: Arc<Shared>,
+}
+
+pub fn message_queue(
+    max_bytes: usize,
+    backpressure: Backpressure,
+) -> (MessageSender, MessageReceiver) {
+    let shared = Arc::new(Shared {
+        state: Mutex::new(State {
+            messages: VecDeque::new(),
+            bytes: 0,
+            sender_alive: true,
+            receiver_alive: true,
+        }),
+        max_bytes,
+        not_empty: Condvar::new(),
+        not_full: Notify::new(),
+        overflowed: AtomicBool::new(false),
+        pushed: AtomicU64::new(0),
+    });
+    (
+        MessageSender {
+            shared: shared.clone(),
+            backpressure,
+        },
+        MessageReceiver { shared },
+    )
+}
+
+impl MessageSender {
+    // waits for room regardless of the backpressure policy; for the startup snapshot and replays
+    pub async fn send(&self, message: MockMessage) -> Result<(), SendError> {
+        loop {
+            {
+                let mut state = self.shared.lock();
+                if !state.receiver_alive {
+                    return Err(SendError::Closed);
+                }
+                if self.shared.has_room(&state, &message) {
+                    self.push(state, message);
+                    return Ok(());
+                }
+            }
+            self.shared.not_full.notified().await;
+        }
+    }
+
+    // applies the backpressure policy if the queue is full; see droppable for what is never dropped
+    pub async fn send_with_backpressure(&self, message: MockMessage) -> Result<Sent, SendError> {
+        let droppable = droppable(&message);
+        let mut dropped = vec![];
+        {
+            let mut state = self.shared.lock();
+            if !state.receiver_alive {
+                return Err(SendError::Closed);
+            }
+            if self.shared.has_room(&state, &message) {
+                self.push(state, message);
+                return Ok(Sent::Queued);
+            }
+            match self.backpressure {
+                Backpressure::DropNewest if droppable => return Ok(Sent::Dropped(vec![message])),
+                Backpressure::DropOldest => {
+                    while !self.shared.has_room(&state, &message) {
+                        let Some(oldest) = state.messages.iter().position(droppable) else {
+                            // only undroppable messages left: wait like for drop-newest
+                            break;
+                        };
+                        let oldest = state.messages.remove(oldest).unwrap();
+                        state.bytes -= oldest.payload_bytes();
+                        dropped.push(oldest);
+                    }
+                    self.shared.update_metrics(&state);
+                    if self.shared.has_room(&state, &message) {
+                        self.push(state, message);
+                        return Ok(Sent::Dropped(dropped));
+                    }
+                }
+                Backpressure::FailRun => {
+                    self.shared.overflowed.store(true, Ordering::Relaxed);
+                    return Err(SendError::Full);
+                }
+                Backpressure::DropNewest | Backpressure::BlockProducer => {}
+            }
+        }
+        self.send(message).await?;
+        if dropped.is_empty() {
+            Ok(Sent::Queued)
+        } else {
+            Ok(Sent::Dropped(dropped))
+        }
+    }
+
+    fn push(&self, mut state: MutexGuard<'_, State>, message: MockMessage) {
+        state.bytes += message.payload_bytes();
+        state.messages.push_back(message);
+        self.shared.pushed.fetch_add(1, Ordering::Relaxed);
+        self.shared.update_metrics(&state);
+        drop(state);
+        self.shared.not_empty.notify_one();
+    }
+}
+
+// slot status updates and the startup snapshot are never dropped, the plugin would miss
+// commitment changes or never leave startup mode
+fn droppable(message: &MockMessage) -> bool {
+    match message {
+        MockMessage::Slot(_) | MockMessage::EndOfStartup => false,
+        MockMessage::Account(mock_account) => !mock_account.is_startup,
+        MockMessage::Transaction(_) | MockMessage::Entry(_) | MockMessage::BlockMeta(_) => true,
+    }
+}
+
+impl Drop for MessageSender {
+    fn drop(&mut self) {
+        self.shared.lock().sender_alive = false;
+        self.shared.not_empty.notify_one();
+    }
+}
+
+impl MessageReceiver {
+    // None once the sender is gone and all messages were received
+    pub fn blocking_recv(&self) -> Option<MockMessage> {
+        let mut state = self.shared.lock();
+        loop {
+            if let Some(message) = state.messages.pop_front() {
+                state.bytes -= message.payload_bytes();
+                self.shared.update_metrics(&state);
+                drop(state);
+                self.shared.not_full.notify_one();
+                return Some(message);
+            }
+            if !state.sender_alive {
+                return None;
+            }
+            state = self.shared.not_empty.wait(state).unwrap();
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.shared.lock().messages.len()
+    }
+
+    // MockMessage::payload_bytes of the queued messages
+    pub fn bytes_in_flight(&self) -> usize {
+        self.shared.lock().bytes
+    }
+
+    // see Shared::pushed
+    pub fn pushed(&self) -> u64 {
+        self.shared.pushed.load(Ordering::Relaxed)
+    }
+
+    pub fn max_bytes(&self) -> usize {
+        self.shared.max_bytes
+    }
+
+    // the traffic was stopped by --backpressure fail-run
+    pub fn overflowed(&self) -> bool {
+        self.shared.overflowed.load(Ordering::Relaxed)
+    }
+}
+
+impl Drop for MessageReceiver {
+    fn drop(&mut self) {
+        let mut state = self.shared.lock();
+        state.receiver_alive = false;
+        state.messages.clear();
+        state.bytes = 0;
+        self.shared.update_metrics(&state);
+        drop(state);
+        self.shared.not_full.notify_one();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::geyser_plugin_util::{MockAccount, MockSlot};
+    use agave_geyser_plugin_interface::geyser_plugin_interface::SlotStatus;
+    use futures::executor::block_on;
+    use solana_sdk::pubkey::Pubkey;
+
+    // a queue of max_bytes=1 holds a single message
+    const SMALL: usize = std::mem::size_of::<MockMessage>();
+
+    fn slot(slot: u64) -> MockMessage {
+        MockMessage::Slot(MockSlot {
+            slot,
+            parent: None,
+            status: SlotStatus::Processed,
+        })
+    }
+
+    fn received(receiver: &MessageReceiver) -> Vec<&'static str> {
+        let mut kinds = vec![];
+        while receiver.len() > 0 {
+            kinds.push(receiver.blocking_recv().unwrap().kind());
+        }
+        kinds
+    }
+
+    fn account(data_len: usize, is_startup: bool) -> MockMessage {
+        MockMessage::Account(MockAccount {
+            slot: 1,
+            pubkey: Pubkey::new_unique(),
+            lamports: 1,
+            data: vec![0; data_len],
+            owner: Pubkey::new_unique(),
+            executable: false,
+            rent_epoch: 0,
+            write_version: 1,
+            is_startup,
+        })
+    }
+
+    #[test]
+    fn drop_newest_keeps_slots() {
+        let (sender, receiver) = message_queue(2 * SMALL, Backpressure::DropNewest);
+        block_on(async {
+            assert!(matches!(
+                sender.send_with_backpressure(slot(1)).await,
+                Ok(Sent::Queued)
+            ));
+            assert!(matches!(
+                sender.send_with_backpressure(account(0, false)).await,
+                Ok(Sent::Queued)
+            ));
+            assert!(matches!(
+                sender.send_with_backpressure(account(0, false)).await,
+                Ok(Sent::Dropped(dropped)) if matches!(dropped[..], [MockMessage::Account(_)])
+            ));
+        });
+        assert_eq!(received(&receiver), vec!["slot", "account"]);
+    }
+
+    #[test]
+    fn drop_oldest_skips_slots_and_startup() {
+        let (sender, receiver) = message_queue(4 * SMALL, Backpressure::DropOldest);
+        block_on(async {
+            sender.send_with_backpressure(slot(1)).await.unwrap();
+            sender
+                .send_with_backpressure(account(0, true))
+                .await
+                .unwrap();
+            sender
+                .send_with_backpressure(MockMessage::EndOfStartup)
+                .await
+                .unwrap();
+            sender
+                .send_with_backpressure(account(0, false))
+                .await
+                .unwrap();
+            // only the account after the startup snapshot makes room
+            assert!(matches!(
+                sender.send_with_backpressure(slot(2)).await,
+                Ok(Sent::Dropped(dropped)) if matches!(
+                    &dropped[..],
+                    [MockMessage::Account(mock_account)] if !mock_account.is_startup
+                )
+            ));
+        });
+        assert_eq!(
+            received(&receiver),
+            vec!["slot", "account", "end_of_startup", "slot"]
+        );
+    }
+
+    #[test]
+    fn block_producer_waits_for_the_receiver() {
+        let (sender, receiver) = message_queue(1, Backpressure::BlockProducer);
+        let consumer = std::thread::spawn(move || {
+            let mut kinds = vec![];
+            while let Some(message) = receiver.blocking_recv() {
+                kinds.push(message.kind());
+            }
+            kinds
+        });
+        block_on(async {
+            for n in 0..100 {
+                assert!(matches!(
+                    sender.send_with_backpressure(slot(n)).await,
+                    Ok(Sent::Queued)
+                ));
+            }
+        });
+        drop(sender);
+        assert_eq!(consumer.join().unwrap().len(), 100);
+    }
+
+    #[test]
+    fn fail_run_and_closed() {
+        let (sender, receiver) = message_queue(1, Backpressure::FailRun);
+        block_on(async {
+            sender.send_with_backpressure(slot(1)).await.unwrap();
+            assert_eq!(
+                sender.send_with_backpressure(slot(2)).await.err(),
+                Some(SendError::Full)
+            );
+        });
+        assert!(receiver.overflowed());
+
+        drop(receiver);
+        assert_eq!(
+            block_on(sender.send(slot(3))).err(),
+            Some(SendError::Closed)
+        );
+    }
+
+    #[test]
+    fn bounded_by_payload_bytes() {
+        let (sender, receiver) = message_queue(10_000 + 2 * SMALL, Backpressure::DropOldest);
+        block_on(async {
+            for _ in 0..3 {
+                sender
+                    .send_with_backpressure(account(4_000, false))
+                    .await
+                    .unwrap();
+            }
+        });
+        // the third account did not fit, the first one was dropped for it
+        assert_eq!(receiver.len(), 2);
+        assert_eq!(receiver.pushed(), 3);
+        assert_eq!(receiver.bytes_in_flight(), 8_000 + 2 * SMALL);
+
+        // a message above the budget only fits into the empty queue
+        block_on(async {
+            match sender.send_with_backpressure(account(20_000, false)).await {
+                Ok(Sent::Dropped(dropped)) => assert_eq!(dropped.len(), 2),
+                other => panic!("unexpected {:?}", other),
+            }
+        });
+        assert_eq!(receiver.bytes_in_flight(), 20_000 + SMALL);
+        receiver.blocking_recv().unwrap();
+        assert_eq!(receiver.bytes_in_flight(), 0);
+    }
+}
@@ -0,0 +1,150 @@
+use crate::geyser_plugin_util::MockMessage;
+use crate::plugin_dispatcher::Callback;
+use hyper::header::CONTENT_TYPE;
+use hyper::service::{make_service_fn, service_fn};
+use hyper::{Body, Request, Response, Server, StatusCode};
+use lazy_static::lazy_static;
+use log::error;
+use prometheus::{
+    exponential_buckets, register_histogram_vec, register_int_counter, register_int_counter_vec,
+    register_int_gauge, register_int_gauge_vec, Encoder, HistogramVec, IntCounter, IntCounterVec,
+    IntGauge, IntGaugeVec, TextEncoder,
+};
+use std::convert::Infallible;
+use std::future::Future;
+use std::net::SocketAddr;
+use std::time::Duration;
+
+lazy_static! {
+    static ref MESSAGES_GENERATED: IntCounterVec = register_int_counter_vec!(
+        "geyser_mock_messages_generated_total",
+        "Messages generated or replayed, including the dropped ones",
+        &["type"]
+    )
+    .unwrap();
+    static ref MESSAGES_DROPPED: IntCounterVec = register_int_counter_vec!(
+        "geyser_mock_messages_dropped_total",
+        "Messages dropped because the channel was full",
+        &["type"]
+    )
+    .unwrap();
+    static ref MESSAGES_DISPATCHED: IntCounterVec = register_int_counter_vec!(
+        "geyser_mock_messages_dispatched_total",
+        "Messages dispatched to the plugins",
+        &["type"]
+    )
+    .unwrap();
+    static ref ACCOUNT_BYTES_GENERATED: IntCounter = register_int_counter!(
+        "geyser_mock_account_bytes_generated_total",
+        "Account data bytes generated or replayed"
+    )
+    .unwrap();
+    static ref CHANNEL_MESSAGES: IntGauge = register_int_gauge!(
+        "geyser_mock_channel_messages",
+        "Messages waiting in the channel to the plugins"
+    )
+    .unwrap();
+    static ref CHANNEL_BYTES: IntGauge = register_int_gauge!(
+        "geyser_mock_channel_bytes",
+        "Payload bytes of the messages waiting in the channel to the plugins"
+    )
+    .unwrap();
+    static ref CHANNEL_MAX_BYTES: IntGauge = register_int_gauge!(
+        "geyser_mock_channel_max_bytes",
+        "Budget of the channel to the plugins in payload bytes"
+    )
+    .unwrap();
+    static ref SLOT: IntGaugeVec = register_int_gauge_vec!(
+        "geyser_mock_slot",
+        "Last slot dispatched with the status",
+        &["status"]
+    )
+    .unwrap();
+    // 1us to ~1s
+    static ref PLUGIN_CALL_DURATION: HistogramVec = register_histogram_vec!(
+        "geyser_mock_plugin_call_duration_seconds",
+        "Duration of the plugin callbacks",
+        &["plugin", "callback"],
+        exponential_buckets(0.000_001, 4.0, 11).unwrap()
+    )
+    .unwrap();
+}
+
+pub fn message_generated(message: &MockMessage) {
+    MESSAGES_GENERATED
+        .with_label_values(&[message.kind()])
+        .inc();
+    if let Some(data_len) = message.account_data_len() {
+        ACCOUNT_BYTES_GENERATED.inc_by(data_len as u64);
+    }
+}
+
+pub fn message_dropped(message: &MockMessage) {
+    MESSAGES_DROPPED.with_label_values(&[message.kind()]).inc();
+}
+
+pub fn channel_max_bytes(max_bytes: usize) {
+    CHANNEL_MAX_BYTES.set(max_bytes as i64);
+}
+
+pub fn channel_filled(messages: usize, bytes: usize) {
+    CHANNEL_MESSAGES.set(messages as i64);
+    CHANNEL_BYTES.set(bytes as i64);
+}
+
+pub fn message_dispatched(message: &MockMessage) {
+    MESSAGES_DISPATCHED
+        .with_label_values(&[message.kind()])
+        .inc();
+    if let MockMessage::Slot(mock_slot) = message {
+        SLOT.with_label_values(&[mock_slot.status.as_str()])
+            .set(mock_slot.slot as i64);
+    }
+}
+
+pub fn plugin_called(plugin: &str, callback: Callback, elapsed: Duration) {
+    PLUGIN_CALL_DURATION
+        .with_label_values(&[plugin, callback.as_str()])
+        .observe(elapsed.as_secs_f64());
+}
+
+// totals of the run for the report, independent of the metrics endpoint
+pub fn messages_generated(kind: &str) -> u64 {
+    MESSAGES_GENERATED.with_label_values(&[kind]).get()
+}
+
+pub fn messages_dropped(kind: &str) -> u64 {
+    MESSAGES_DROPPED.with_label_values(&[kind]).get()
+}
+
+pub fn account_bytes_generated() -> u64 {
+    ACCOUNT_BYTES_GENERATED.get()
+}
+
+// binds right away so a port clash (e.g. with the prometheus port of the plugin) shows up at startup
+pub fn serve(addr: &SocketAddr) -> hyper::Result<impl Future<Output = ()>> {
+    let server = Server::try_bind(addr)?.serve(make_service_fn(|_| async {
+        Ok::<_, Infallible>(service_fn(handle_request))
+    }));
+    Ok(async move {
+        if let Err(err) = server.await {
+            error!("metrics endpoint failed: {}", err);
+        }
+    })
+}
+
+async fn handle_request(request: Request<Body>) -> Result<Response<Body>, Infallible> {
+    if request.uri().path() != "/metrics" {
+        return Ok(Response::builder()
+            .status(StatusCode::NOT_FOUND)
+            .body(Body::empty())
+            .unwrap());
+    }
+    let encoder = TextEncoder::new();
+    let mut buffer = vec![];
+    encoder.encode(&prometheus::gather(), &mut buffer).unwrap();
+    Ok(Response::builder()
+        .header(CONTENT_TYPE, encoder.format_type())
+        .body(Body::from(buffer))
+        .unwrap())
+}
@@ -1,53 +1,240 @@
-use bytes::{Bytes, BytesMut};
-use rand::distributions::Standard;
-use rand::{random, thread_rng, Rng, RngCore};
-use solana_sdk::clock::UnixTimestamp;
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::Rng;
 use solana_sdk::pubkey::Pubkey;
 // use solana_sdk::recent_blockhashes_account::update_account;
+use crate::account_pool::AccountPool;
+use crate::account_sizes::AccountSizeDistribution;
 use crate::debouncer_instant;
-use crate::geyser_plugin_util::{MockAccount, MockMessage, MockSlot};
-use agave_geyser_plugin_interface::geyser_plugin_interface::ReplicaAccountInfoV3;
-use libloading::Library;
+use crate::fork_simulator::ForkSimulator;
+use crate::geyser_plugin_util::{
+    MockAccount, MockBlockMeta, MockEntry, MockMessage, MockSlot, MockTransaction,
+};
+use crate::message_queue::{MessageSender, SendError, Sent};
+use crate::metrics;
+use crate::pacing::Pacing;
+use crate::plugin_dispatcher::NotificationsEnabled;
+use crate::skip_simulator::SkipSimulator;
+use agave_geyser_plugin_interface::geyser_plugin_interface::SlotStatus;
 use log::{debug, error, info, warn};
 use solana_program::clock::Slot;
-use solana_sdk::account::{Account, AccountSharedData};
-use solana_sdk::commitment_config::CommitmentLevel::{Confirmed, Finalized, Processed};
-use std::ops::Add;
-use std::path::Path;
-use std::thread::{sleep, spawn};
+use solana_sdk::clock::UnixTimestamp;
+use solana_sdk::hash::{hashv, Hash};
+use solana_sdk::message::{Message, SimpleAddressLoader};
+use solana_sdk::reward_type::RewardType;
+use solana_sdk::signature::Signature;
+use solana_sdk::system_instruction;
+use solana_sdk::transaction::{
+    MessageHash, SanitizedTransaction, Transaction, VersionedTransaction,
+};
+use solana_sdk::vote;
+use solana_sdk::vote::state::TowerSync;
+use solana_transaction_status::{Reward, RewardsAndNumPartitions, TransactionStatusMeta};
+use std::collections::{BTreeMap, HashSet};
 use std::time::{Duration, SystemTime, UNIX_EPOCH};
-use tokio::sync::mpsc::error::TrySendError;
-use tokio::sync::mpsc::{Sender, UnboundedSender};
+use tokio::sync::mpsc::UnboundedSender;
 use tokio::time::Instant;
 
+// number of voting validators on mainnet
+const VALIDATOR_COUNT: usize = 1500;
+
+const SNAPSHOT_BLOCK_HEIGHT: u64 = 39_000_000;
+
+// used instead of the wall clock for deterministic runs
+const FIRST_SLOT_BLOCK_TIME: UnixTimestamp = 1_700_000_000;
+
+// mainnet-beta
+const HASHES_PER_TICK: u64 = 62_500;
+
+pub const FIRST_SLOT: Slot = 42_000_000;
+
+const SLOT_DURATION: Duration = Duration::from_millis(400);
+
+// same for all generated account updates
+const MOCK_WRITE_VERSION: u64 = 999999;
+
+// like the accounts loaded from a snapshot at validator startup; blocks if the plugin is slow
+pub async fn startup_snapshot(
+    geyser_channel: &MessageSender,
+    rng: &mut StdRng,
+    accounts_count: u64,
+    account_sizes: &AccountSizeDistribution,
+    owners_count: u64,
+    compressibility: f64,
+) -> bool {
+    info!(
+        "Setup startup snapshot with {} accounts owned by {} programs",
+        accounts_count, owners_count
+    );
+    let owners: Vec<Pubkey> = (0..owners_count.max(1))
+        .map(|_| new_rand_pubkey(rng))
+        .collect();
+    let snapshot_slot = FIRST_SLOT - 1;
+
+    let debouncer = debouncer_instant::Debouncer::new(Duration::from_secs(1));
+    let started_at = Instant::now();
+    let mut bytes_total = 0;
+
+    for i in 0..accounts_count {
+        let data_bytes = account_sizes.sample(rng);
+        bytes_total += data_bytes;
+
+        let account = MockAccount {
+            slot: snapshot_slot,
+            pubkey: new_rand_pubkey(rng),
+            lamports: rng.gen_range(890_880, 10_000_000_000),
+            data: build_account_data(data_bytes, compressibility, rng),
+            owner: *owners.choose(rng).unwrap(),
+            executable: false,
+            rent_epoch: u64::MAX,
+            write_version: MOCK_WRITE_VERSION,
+            is_startup: true,
+        };
+
+        let message = MockMessage::Account(account);
+        metrics::message_generated(&message);
+        if geyser_channel.send(message).await.is_err() {
+            error!("channel was closed - shutting down");
+            return false;
+        }
+
+        if debouncer.can_fire() {
+            info!(
+                "sent {} of {} startup accounts ({} bytes)",
+                i, accounts_count, bytes_total
+            );
+        }
+    }
+
+    metrics::message_generated(&MockMessage::EndOfStartup);
+    if geyser_channel
+        .send(MockMessage::EndOfStartup)
+        .await
+        .is_err()
+    {
+        error!("channel was closed - shutting down");
+        return false;
+    }
+
+    info!(
+        "sent {} startup accounts ({} bytes) in {:.3}s",
+        accounts_count,
+        bytes_total,
+        started_at.elapsed().as_secs_f64()
+    );
+
+    true
+}
+
 // - 20-80 MiB per Slot
 // 4000 updates per Slot
+pub struct MainnetTrafficConfig {
+    pub bytes_per_slot: u64,
+    pub account_sizes: AccountSizeDistribution,
+    pub account_pool_size: u64,
+    pub account_owners: u64,
+    pub account_zipf_exponent: f64,
+    pub compressibility: f64,
+    // over which the account updates are spread within a slot
+    pub slot_tick_delay: Duration,
+    pub pacing: Pacing,
+    pub transactions_per_slot: u64,
+    pub vote_transaction_ratio: f64,
+    pub entries_per_slot: u64,
+    pub fork_rate: f64,
+    pub fork_length: u64,
+    pub skip_rate: f64,
+    pub leader_skip_rate: f64,
+    pub rewards_per_block: u64,
+    pub reward_partitions: Option<u64>,
+    // lamports and block_time from the wall clock; otherwise derived from rng and slot
+    pub wall_clock: bool,
+    // disabled kinds are not generated at all; the block still counts the transactions and entries
+    pub notifications_enabled: NotificationsEnabled,
+}
+
 pub async fn mainnet_traffic(
-    geyser_channel: Sender<MockMessage>,
-    bytes_per_slot: u64,
-    compressibility: f64,
-    slot_tick_delay: f64,
+    geyser_channel: MessageSender,
+    config: MainnetTrafficConfig,
+    mut rng: StdRng,
 ) {
+    let MainnetTrafficConfig {
+        bytes_per_slot,
+        account_sizes,
+        account_pool_size,
+        account_owners,
+        account_zipf_exponent,
+        compressibility,
+        slot_tick_delay,
+        pacing,
+        transactions_per_slot,
+        vote_transaction_ratio,
+        entries_per_slot,
+        fork_rate,
+        fork_length,
+        skip_rate,
+        leader_skip_rate,
+        rewards_per_block,
+        reward_partitions,
+        wall_clock,
+        notifications_enabled,
+    } = config;
     info!(
         "Setup mainnet-like traffic source with {} bytes per slot and compressibility {}",
         bytes_per_slot, compressibility
     );
-    let owner = Pubkey::new_unique();
-    let account_pubkeys: Vec<Pubkey> = (0..100).map(|_| Pubkey::new_unique()).collect();
+    info!(
+        "Setup {} transactions per slot with vote ratio {}",
+        transactions_per_slot, vote_transaction_ratio
+    );
+    info!("Setup {} entries per slot", entries_per_slot);
+    info!(
+        "Setup fork rate {} with {} slots per abandoned fork",
+        fork_rate, fork_length
+    );
+    info!(
+        "Setup skip rate {} and leader window skip rate {}",
+        skip_rate, leader_skip_rate
+    );
+    info!(
+        "Setup account pool with {} accounts owned by {} programs (zipf exponent {})",
+        account_pool_size, account_owners, account_zipf_exponent
+    );
+    let account_pool = AccountPool::new(
+        account_pool_size,
+        account_owners,
+        account_zipf_exponent,
+        &mut rng,
+    );
+    // (identity, vote account) pairs
+    let validators: Vec<(Pubkey, Pubkey)> = (0..VALIDATOR_COUNT)
+        .map(|_| (new_rand_pubkey(&mut rng), new_rand_pubkey(&mut rng)))
+        .collect();
+
+    // blockhash and block height of recent blocks on all forks
+    let mut recent_blocks: BTreeMap<Slot, (Hash, u64)> = BTreeMap::new();
+    recent_blocks.insert(
+        FIRST_SLOT - 1,
+        (new_rand_hash(&mut rng), SNAPSHOT_BLOCK_HEIGHT),
+    );
+    let mut fork_simulator = ForkSimulator::new(FIRST_SLOT - 1, fork_rate, fork_length);
+    let mut skip_simulator = SkipSimulator::new(skip_rate, leader_skip_rate);
 
     let mut dropped_total = 0;
     let debouncer = debouncer_instant::Debouncer::new(std::time::Duration::from_millis(10));
 
-    for slot in 42_000_000.. {
+    for slot in FIRST_SLOT.. {
         let slot_started_at = Instant::now();
 
-        let sizes = vec![
-            // mainnet distribution
-            0, 8, 8, 165, 165, 165, 165, 11099, 11099, 11099, 11099, 11099,
-            11099,
-            // shape with a lot larger sizes
-            // 200000, 220000, 230000,
-        ];
+        if skip_simulator.is_skipped(slot, &mut rng) {
+            debug!("skipping slot {}", slot);
+            pacing.sleep_until(slot_started_at, SLOT_DURATION).await;
+            continue;
+        }
+
+        let parent = fork_simulator.new_slot(slot, &mut rng);
+        let (parent_blockhash, parent_block_height) = recent_blocks[&parent];
+
         // 10MB -> stream buffer size peaks at 30
         // 30MB -> stream buffer size peaks at 10000th and more
         // per slot
@@ -55,8 +242,8 @@ pub async fn mainnet_traffic(
 
         let mut requested_sizes: Vec<u64> = Vec::new();
 
-        for i in 0..99_999_999 {
-            let data_size = sizes[i % sizes.len()];
+        for _ in 0..99_999_999 {
+            let data_size = account_sizes.sample(&mut rng);
 
             if bytes_total + data_size > bytes_per_slot {
                 break;
@@ -73,116 +260,396 @@ pub async fn mainnet_traffic(
             requested_sizes.len()
         );
 
-        // distribute data over the slot duration (400ms) but leave some space
-        let avg_delay = slot_tick_delay/ requested_sizes.len() as f64;
-
-        for (i, data_bytes) in requested_sizes.into_iter().enumerate() {
-            let next_message_at =
-                slot_started_at.add(Duration::from_secs_f64(avg_delay * i as f64));
-
-            let account_build_started_at = Instant::now();
-            let mut data = vec![0; data_bytes as usize];
-            let entropy_bytes = (data_bytes as f64 * (1.0 - compressibility)) as usize;
-            assert!(
-                entropy_bytes <= data_bytes as usize,
-                "entropy_bytes overflow"
-            );
-            fill_with_xor_prng(&mut data[0..entropy_bytes]);
-            let data = data.to_vec();
-
-            let account_pubkey = account_pubkeys[i % sizes.len()];
+        // distribute data over the slot duration (400ms) but leave some space; no accounts might fit
+        let avg_delay = slot_tick_delay
+            .checked_div(requested_sizes.len() as u32)
+            .unwrap_or_default();
 
-            let epoch_us = SystemTime::now()
-                .duration_since(UNIX_EPOCH)
-                .unwrap()
-                .as_micros() as u64;
+        let accounts_count = requested_sizes.len() as u64;
+        let mut transactions_sent = 0;
 
-            let account = MockAccount {
-                slot,
-                pubkey: account_pubkey,
-                lamports: epoch_us,
-                data,
-                owner,
-                executable: false,
-                rent_epoch: 0,
-            };
+        for (i, data_bytes) in requested_sizes.into_iter().enumerate() {
+            let next_message_offset = avg_delay.mul_f64(i as f64);
+
+            if notifications_enabled.accounts {
+                let data = build_account_data(data_bytes, compressibility, &mut rng);
+
+                let (account_pubkey, owner) = account_pool.sample(&mut rng);
+
+                // clients use the timestamp to measure the delay
+                let lamports = if wall_clock {
+                    SystemTime::now()
+                        .duration_since(UNIX_EPOCH)
+                        .unwrap()
+                        .as_micros() as u64
+                } else {
+                    rng.gen_range(890_880, 10_000_000_000)
+                };
+
+                let account = MockAccount {
+                    slot,
+                    pubkey: account_pubkey,
+                    lamports,
+                    data,
+                    owner,
+                    executable: false,
+                    rent_epoch: 0,
+                    write_version: MOCK_WRITE_VERSION,
+                    is_startup: false,
+                };
+
+                let sent_result = send(&geyser_channel, MockMessage::Account(account)).await;
+                if !handle_sent_result(sent_result, &mut dropped_total, &debouncer) {
+                    return;
+                }
+            }
 
-            let sent_result = geyser_channel.try_send(MockMessage::Account(account));
-
-            match sent_result {
-                Ok(_) => {}
-                Err(TrySendError::Full(_)) => {
-                    dropped_total += 1;
-                    if debouncer.can_fire() {
-                        warn!(
-                            "channel is full (total drops: {}) - dropping message",
-                            dropped_total
-                        );
+            // interleave the transactions with the account updates
+            let transactions_due = transactions_per_slot * (i as u64 + 1) / accounts_count;
+            while transactions_sent < transactions_due {
+                if notifications_enabled.transactions {
+                    let is_vote = rng.gen_bool(vote_transaction_ratio);
+                    let transaction = build_mock_transaction(
+                        slot,
+                        transactions_sent as usize,
+                        is_vote,
+                        &validators,
+                        &account_pool,
+                        &mut rng,
+                    );
+                    let sent_result = send(
+                        &geyser_channel,
+                        MockMessage::Transaction(Box::new(transaction)),
+                    )
+                    .await;
+                    if !handle_sent_result(sent_result, &mut dropped_total, &debouncer) {
+                        return;
                     }
                 }
-                Err(TrySendError::Closed(_)) => {
-                    error!("channel was closed - shutting down");
+                transactions_sent += 1;
+            }
+
+            pacing
+                .sleep_until(slot_started_at, next_message_offset)
+                .await;
+        }
+
+        // no account updates in this slot to interleave with
+        while transactions_sent < transactions_per_slot {
+            if notifications_enabled.transactions {
+                let is_vote = rng.gen_bool(vote_transaction_ratio);
+                let transaction = build_mock_transaction(
+                    slot,
+                    transactions_sent as usize,
+                    is_vote,
+                    &validators,
+                    &account_pool,
+                    &mut rng,
+                );
+                let sent_result = send(
+                    &geyser_channel,
+                    MockMessage::Transaction(Box::new(transaction)),
+                )
+                .await;
+                if !handle_sent_result(sent_result, &mut dropped_total, &debouncer) {
                     return;
                 }
             }
+            transactions_sent += 1;
+        }
 
-            tokio::time::sleep_until(next_message_at).await;
+        // the entries continue the hash chain of the parent block; built anyway for the blockhash
+        let mut last_entry_hash = parent_blockhash;
+        for entry in build_mock_entries(
+            slot,
+            entries_per_slot,
+            transactions_per_slot,
+            &mut last_entry_hash,
+            &mut rng,
+        ) {
+            if !notifications_enabled.entries {
+                continue;
+            }
+            let sent_result = send(&geyser_channel, MockMessage::Entry(entry)).await;
+            if !handle_sent_result(sent_result, &mut dropped_total, &debouncer) {
+                return;
+            }
         }
 
-        let block_time = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .unwrap()
-            .as_secs() as UnixTimestamp;
+        // the blockhash is the hash of the last entry
+        let blockhash = if entries_per_slot > 0 {
+            last_entry_hash
+        } else {
+            hashv(&[parent_blockhash.as_ref(), &slot.to_le_bytes()])
+        };
+        let block_height = parent_block_height + 1;
+        recent_blocks.insert(slot, (blockhash, block_height));
+        // independent of the slot distance, a long run of skipped slots must keep the tips
+        let possible_parents = fork_simulator.possible_parents();
+        recent_blocks.retain(|slot, _| possible_parents.contains(slot));
+
+        let block_time = if wall_clock {
+            SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap()
+                .as_secs() as UnixTimestamp
+        } else {
+            FIRST_SLOT_BLOCK_TIME + ((slot - FIRST_SLOT) * 400 / 1000) as UnixTimestamp
+        };
+
+        let block_meta = MockBlockMeta {
+            slot,
+            parent_slot: parent,
+            parent_blockhash: parent_blockhash.to_string(),
+            blockhash: blockhash.to_string(),
+            rewards: RewardsAndNumPartitions {
+                rewards: build_mock_rewards(
+                    rewards_per_block,
+                    transactions_per_slot,
+                    &validators,
+                    &mut rng,
+                ),
+                num_partitions: reward_partitions,
+            },
+            block_time: Some(block_time),
+            block_height: Some(block_height),
+            executed_transaction_count: transactions_per_slot,
+            entry_count: entries_per_slot,
+        };
 
         let mut sent_results = vec![];
 
-        let sent_result = geyser_channel.try_send(MockMessage::Slot(MockSlot {
-            slot,
-            commitment_level: Processed,
-        }));
+        // TODO FirstShredReceived, Completed, CreatedBank and Dead before/instead of Processed
+        // once we are on agave-geyser-plugin-interface 2.1 (SlotStatus in 2.0 has only these three)
+        let sent_result = send(
+            &geyser_channel,
+            MockMessage::Slot(MockSlot {
+                slot,
+                parent: Some(parent),
+                status: SlotStatus::Processed,
+            }),
+        )
+        .await;
         sent_results.push(sent_result);
 
-        {
-            let slot_confirmed = slot - 2;
-            let sent_result = geyser_channel.try_send(MockMessage::Slot(MockSlot {
-                slot: slot_confirmed,
-                commitment_level: Confirmed,
-            }));
-            sent_results.push(sent_result);
-        }
+        let sent_result = send(&geyser_channel, MockMessage::BlockMeta(block_meta)).await;
+        sent_results.push(sent_result);
 
-        {
-            let slot_finalized = slot - 32;
-            let sent_result = geyser_channel.try_send(MockMessage::Slot(MockSlot {
-                slot: slot_finalized,
-                commitment_level: Finalized,
-            }));
+        for mock_slot in fork_simulator.commitment_updates() {
+            let sent_result = send(&geyser_channel, MockMessage::Slot(mock_slot)).await;
             sent_results.push(sent_result);
         }
 
         for sent_result in sent_results {
-            match sent_result {
-                Ok(_) => {}
-                Err(TrySendError::Full(_)) => {
-                    dropped_total += 1;
-                    if debouncer.can_fire() {
-                        warn!(
-                            "channel is full (total drops: {}) - dropping message",
-                            dropped_total
-                        );
-                    }
-                }
-                Err(TrySendError::Closed(_)) => {
-                    error!("channel was closed - shutting down");
-                    return;
-                }
+            if !handle_sent_result(sent_result, &mut dropped_total, &debouncer) {
+                return;
             }
         }
 
-        tokio::time::sleep_until(slot_started_at.add(Duration::from_millis(400))).await;
+        pacing.sleep_until(slot_started_at, SLOT_DURATION).await;
     }
 }
 
+// counts the message as generated even if it gets dropped
+async fn send(geyser_channel: &MessageSender, message: MockMessage) -> Result<Sent, SendError> {
+    metrics::message_generated(&message);
+    geyser_channel.send_with_backpressure(message).await
+}
+
+// returns false if the traffic must stop
+fn handle_sent_result(
+    sent_result: Result<Sent, SendError>,
+    dropped_total: &mut u64,
+    debouncer: &debouncer_instant::Debouncer,
+) -> bool {
+    match sent_result {
+        Ok(Sent::Queued) => true,
+        Ok(Sent::Dropped(messages)) => {
+            for message in &messages {
+                metrics::message_dropped(message);
+            }
+            *dropped_total += messages.len() as u64;
+            if debouncer.can_fire() {
+                warn!(
+                    "channel is full (total drops: {}) - dropping {} message",
+                    dropped_total,
+                    messages[0].kind()
+                );
+            }
+            true
+        }
+        Err(SendError::Full) => {
+            error!("channel is full - stopping the traffic (backpressure fail-run)");
+            false
+        }
+        Err(SendError::Closed) => {
+            error!("channel was closed - shutting down");
+            false
+        }
+    }
+}
+
+// vote transactions (TowerSync) or simple transfers; signatures are random as nobody verifies them
+fn build_mock_transaction(
+    slot: Slot,
+    index: usize,
+    is_vote: bool,
+    validators: &[(Pubkey, Pubkey)],
+    account_pool: &AccountPool,
+    rng: &mut impl Rng,
+) -> MockTransaction {
+    const FEE: u64 = 5000;
+    const PRE_BALANCE: u64 = 1_000_000_000;
+
+    let (instruction, payer, transfer_lamports, compute_units, log_messages) = if is_vote {
+        let (identity, vote_account) = validators[rng.gen_range(0, validators.len())];
+        // full tower with 31 lockouts like on mainnet
+        let lockouts: Vec<(Slot, u32)> = (1..=31u32)
+            .rev()
+            .map(|confirmation_count| {
+                (
+                    slot.saturating_sub(confirmation_count as u64),
+                    confirmation_count,
+                )
+            })
+            .collect();
+        let mut tower_sync = TowerSync::from(lockouts);
+        tower_sync.hash = new_rand_hash(rng);
+        tower_sync.block_id = new_rand_hash(rng);
+        let instruction = vote::instruction::tower_sync(&vote_account, &identity, tower_sync);
+        let log_messages = vec![
+            format!("Program {} invoke [1]", vote::program::id()),
+            format!("Program {} success", vote::program::id()),
+        ];
+        (instruction, identity, 0, 2100, log_messages)
+    } else {
+        let (from, _) = account_pool.sample(rng);
+        let to = new_rand_pubkey(rng);
+        let lamports = rng.gen_range(1, 1_000_000);
+        let instruction = system_instruction::transfer(&from, &to, lamports);
+        let log_messages = vec![
+            format!("Program {} invoke [1]", solana_sdk::system_program::id()),
+            format!("Program {} success", solana_sdk::system_program::id()),
+        ];
+        (instruction, from, lamports, 150, log_messages)
+    };
+
+    let message = Message::new_with_blockhash(&[instruction], Some(&payer), &new_rand_hash(rng));
+    let mut transaction = Transaction::new_unsigned(message);
+    transaction.signatures = (0..transaction.message.header.num_required_signatures)
+        .map(|_| new_rand_signature(rng))
+        .collect();
+    let signature = transaction.signatures[0];
+
+    let transaction = SanitizedTransaction::try_create(
+        VersionedTransaction::from(transaction),
+        MessageHash::Compute,
+        Some(is_vote),
+        SimpleAddressLoader::Disabled,
+        &HashSet::new(),
+    )
+    .expect("mock transaction must be valid");
+
+    // account keys: payer first, transfer recipient second
+    let pre_balances = vec![PRE_BALANCE; transaction.message().account_keys().len()];
+    let mut post_balances = pre_balances.clone();
+    post_balances[0] -= FEE + transfer_lamports;
+    if !is_vote {
+        post_balances[1] += transfer_lamports;
+    }
+
+    let transaction_status_meta = TransactionStatusMeta {
+        fee: FEE,
+        pre_balances,
+        post_balances,
+        inner_instructions: Some(vec![]),
+        log_messages: Some(log_messages),
+        pre_token_balances: Some(vec![]),
+        post_token_balances: Some(vec![]),
+        rewards: Some(vec![]),
+        compute_units_consumed: Some(compute_units),
+        ..TransactionStatusMeta::default()
+    };
+
+    MockTransaction {
+        slot,
+        signature,
+        is_vote,
+        transaction,
+        transaction_status_meta,
+        index,
+    }
+}
+
+// fee reward for the leader followed by staking rewards
+fn build_mock_rewards(
+    rewards_per_block: u64,
+    transactions_count: u64,
+    validators: &[(Pubkey, Pubkey)],
+    rng: &mut impl Rng,
+) -> Vec<Reward> {
+    (0..rewards_per_block)
+        .map(|i| {
+            let (identity, vote_account) = validators[rng.gen_range(0, validators.len())];
+            let post_balance = rng.gen_range(1_000_000_000, 100_000_000_000);
+            if i == 0 {
+                // half of the fees go to the leader
+                Reward {
+                    pubkey: identity.to_string(),
+                    lamports: (transactions_count * 5000 / 2) as i64,
+                    post_balance,
+                    reward_type: Some(RewardType::Fee),
+                    commission: None,
+                }
+            } else {
+                Reward {
+                    pubkey: vote_account.to_string(),
+                    lamports: rng.gen_range(1_000, 10_000_000),
+                    post_balance,
+                    reward_type: Some(RewardType::Staking),
+                    commission: Some(rng.gen_range(0, 11)),
+                }
+            }
+        })
+        .collect()
+}
+
+// split the transactions of the slot over the entries; entries without transactions are ticks
+fn build_mock_entries(
+    slot: Slot,
+    entries_per_slot: u64,
+    transactions_count: u64,
+    last_entry_hash: &mut Hash,
+    rng: &mut impl Rng,
+) -> Vec<MockEntry> {
+    (0..entries_per_slot)
+        .map(|i| {
+            let starting_transaction_index = transactions_count * i / entries_per_slot;
+            let next_transaction_index = transactions_count * (i + 1) / entries_per_slot;
+            let executed_transaction_count = next_transaction_index - starting_transaction_index;
+
+            let num_hashes = if executed_transaction_count == 0 {
+                HASHES_PER_TICK
+            } else {
+                rng.gen_range(1, HASHES_PER_TICK)
+            };
+            // not the real PoH hash chain (too expensive) but still chained
+            let hash = hashv(&[last_entry_hash.as_ref(), &num_hashes.to_le_bytes()]);
+            *last_entry_hash = hash;
+
+            MockEntry {
+                slot,
+                index: i as usize,
+                num_hashes,
+                hash,
+                executed_transaction_count,
+                starting_transaction_index: starting_transaction_index as usize,
+            }
+        })
+        .collect()
+}
+
+#[allow(dead_code)]
 pub async fn helloworld_traffic(grpc_channel: UnboundedSender<MockAccount>) {
     loop {
         let account_mock = MockAccount {
@@ -193,6 +660,8 @@ pub async fn helloworld_traffic(grpc_channel: UnboundedSender<MockAccount>) {
             executable: false,
             rent_epoch: 0,
             data: vec![1, 2, 3],
+            write_version: MOCK_WRITE_VERSION,
+            is_startup: false,
         };
 
         grpc_channel.send(account_mock).expect("send");
@@ -202,9 +671,36 @@ pub async fn helloworld_traffic(grpc_channel: UnboundedSender<MockAccount>) {
     }
 }
 
-fn fill_with_xor_prng(binary: &mut [u8]) {
+fn new_rand_pubkey(rng: &mut impl Rng) -> Pubkey {
+    Pubkey::new_from_array(rng.gen())
+}
+
+fn new_rand_hash(rng: &mut impl Rng) -> Hash {
+    Hash::new_from_array(rng.gen())
+}
+
+fn new_rand_signature(rng: &mut impl Rng) -> Signature {
+    let mut bytes = [0u8; 64];
+    rng.fill_bytes(&mut bytes);
+    Signature::from(bytes)
+}
+
+fn build_account_data(data_bytes: u64, compressibility: f64, rng: &mut impl Rng) -> Vec<u8> {
+    let mut data = vec![0; data_bytes as usize];
+    let entropy_bytes = (data_bytes as f64 * (1.0 - compressibility)) as usize;
+    assert!(
+        entropy_bytes <= data_bytes as usize,
+        "entropy_bytes overflow"
+    );
+    // xorshift state must not be zero
+    fill_with_xor_prng(&mut data[0..entropy_bytes], rng.gen::<u32>() | 1);
+    data
+}
+
+#[allow(clippy::identity_op)]
+fn fill_with_xor_prng(binary: &mut [u8], seed: u32) {
     let seed_n = binary.len();
-    let mut state: u32 = 0xdeadbeef;
+    let mut state: u32 = seed;
     for i_word in 0..seed_n / 4 {
         let mut x = state;
         x ^= x << 13;
@@ -222,11 +718,12 @@ fn fill_with_xor_prng(binary: &mut [u8]) {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use rand::SeedableRng;
 
     #[test]
     fn fill_with_xor_prng_test() {
         let mut data_full_entropy = vec![0; 1000];
-        fill_with_xor_prng(&mut data_full_entropy);
+        fill_with_xor_prng(&mut data_full_entropy, 0xdeadbeef);
         let compressed_size = lz4_flex::compress(&data_full_entropy).len();
         assert_eq!(compressed_size, 1005);
     }
@@ -234,8 +731,90 @@ mod tests {
     #[test]
     fn fill_with_xor_prng_lowentropy_test() {
         let mut data_low_entropy = vec![0; 1000];
-        fill_with_xor_prng(&mut data_low_entropy[0..200]);
+        fill_with_xor_prng(&mut data_low_entropy[0..200], 0xdeadbeef);
         let compressed_size = lz4_flex::compress(&data_low_entropy).len();
         assert_eq!(compressed_size, 219);
     }
+
+    #[test]
+    fn build_mock_transaction_test() {
+        let mut rng = StdRng::seed_from_u64(42);
+        let validators = vec![(Pubkey::new_unique(), Pubkey::new_unique())];
+        let account_pool = AccountPool::new(10, 1, 0.0, &mut rng);
+
+        let vote = build_mock_transaction(42, 0, true, &validators, &account_pool, &mut rng);
+        assert!(vote.transaction.is_simple_vote_transaction());
+        assert_eq!(vote.signature, *vote.transaction.signature());
+        // the tower, account keys and log messages count towards --channel-max-bytes
+        let vote_bytes = MockMessage::Transaction(Box::new(vote)).payload_bytes();
+        assert!(
+            vote_bytes
+                > std::mem::size_of::<MockMessage>() + std::mem::size_of::<MockTransaction>() + 300,
+            "vote transaction payload was {} bytes",
+            vote_bytes
+        );
+
+        let transfer = build_mock_transaction(42, 1, false, &validators, &account_pool, &mut rng);
+        assert!(!transfer.transaction.is_simple_vote_transaction());
+        let meta = &transfer.transaction_status_meta;
+        assert_eq!(
+            meta.pre_balances.iter().sum::<u64>() - meta.fee,
+            meta.post_balances.iter().sum::<u64>()
+        );
+    }
+
+    #[test]
+    fn build_mock_entries_test() {
+        let mut rng = StdRng::seed_from_u64(42);
+        let mut last_entry_hash = Hash::default();
+        let entries = build_mock_entries(42, 64, 1000, &mut last_entry_hash, &mut rng);
+
+        assert_eq!(entries.len(), 64);
+        assert_eq!(
+            entries
+                .iter()
+                .map(|entry| entry.executed_transaction_count)
+                .sum::<u64>(),
+            1000
+        );
+        for pair in entries.windows(2) {
+            assert_eq!(
+                pair[0].starting_transaction_index + pair[0].executed_transaction_count as usize,
+                pair[1].starting_transaction_index
+            );
+        }
+        assert_eq!(last_entry_hash, entries.last().unwrap().hash);
+    }
+
+    #[test]
+    fn build_mock_rewards_test() {
+        let mut rng = StdRng::seed_from_u64(42);
+        let validators = vec![(Pubkey::new_unique(), Pubkey::new_unique())];
+
+        let rewards = build_mock_rewards(3, 1000, &validators, &mut rng);
+        assert_eq!(rewards.len(), 3);
+        assert_eq!(rewards[0].reward_type, Some(RewardType::Fee));
+        assert_eq!(rewards[0].pubkey, validators[0].0.to_string());
+        assert_eq!(rewards[0].lamports, 2_500_000);
+        assert_eq!(rewards[1].reward_type, Some(RewardType::Staking));
+
+        assert!(build_mock_rewards(0, 1000, &validators, &mut rng).is_empty());
+    }
+
+    #[test]
+    fn seeded_generation_is_deterministic() {
+        let validators = vec![(Pubkey::new_unique(), Pubkey::new_unique())];
+        let account_pool = AccountPool::new(10, 1, 0.0, &mut StdRng::seed_from_u64(0));
+
+        let build = |seed: u64| {
+            let mut rng = StdRng::seed_from_u64(seed);
+            let data = build_account_data(1000, 0.5, &mut rng);
+            let transaction =
+                build_mock_transaction(42, 0, false, &validators, &account_pool, &mut rng);
+            (data, transaction.signature)
+        };
+
+        assert_eq!(build(7), build(7));
+        assert_ne!(build(7), build(8));
+    }
 }
@@ -0,0 +1,85 @@
+use serde_derive::Serialize;
+use std::ops::Add;
+use std::str::FromStr;
+use std::time::Duration;
+use tokio::time::Instant;
+
+// how fast the traffic sources emit their messages compared to the original timing
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub enum Pacing {
+    RealTime,
+    // e.g. 2.0 runs twice as fast
+    Multiplier(f64),
+    // no sleeping at all; max throughput for saturation benchmarks
+    Unthrottled,
+}
+
+impl FromStr for Pacing {
+    type Err = String;
+
+    // realtime, 2x, 0.5x, max
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "realtime" => Ok(Pacing::RealTime),
+            "max" | "unthrottled" => Ok(Pacing::Unthrottled),
+            _ => {
+                let multiplier: f64 = s
+                    .strip_suffix('x')
+                    .and_then(|multiplier| multiplier.parse().ok())
+                    .ok_or_else(|| {
+                        format!("expected realtime, max or a multiplier like 10x: {}", s)
+                    })?;
+                if !(multiplier > 0.0 && multiplier.is_finite()) {
+                    return Err(format!("multiplier must be positive: {}", s));
+                }
+                Ok(Pacing::Multiplier(multiplier))
+            }
+        }
+    }
+}
+
+impl Pacing {
+    // None if there is no waiting
+    pub fn scale(&self, offset: Duration) -> Option<Duration> {
+        match self {
+            Pacing::RealTime => Some(offset),
+            Pacing::Multiplier(multiplier) => Some(offset.div_f64(*multiplier)),
+            Pacing::Unthrottled => None,
+        }
+    }
+
+    // waits until offset (in original time) after the anchor
+    pub async fn sleep_until(&self, anchor: Instant, offset: Duration) {
+        match self.scale(offset) {
+            Some(scaled) => tokio::time::sleep_until(anchor.add(scaled)).await,
+            // give other tasks a chance to run
+            None => tokio::task::yield_now().await,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_pacing() {
+        assert_eq!("realtime".parse(), Ok(Pacing::RealTime));
+        assert_eq!("max".parse(), Ok(Pacing::Unthrottled));
+        assert_eq!("10x".parse(), Ok(Pacing::Multiplier(10.0)));
+        assert_eq!("0.5x".parse(), Ok(Pacing::Multiplier(0.5)));
+        assert!("0x".parse::<Pacing>().is_err());
+        assert!("fast".parse::<Pacing>().is_err());
+    }
+
+    #[test]
+    fn scale_offset() {
+        let offset = Duration::from_millis(400);
+        assert_eq!(Pacing::RealTime.scale(offset), Some(offset));
+        assert_eq!(
+            Pacing::Multiplier(4.0).scale(offset),
+            Some(Duration::from_millis(100))
+        );
+        assert_eq!(Pacing::Unthrottled.scale(offset), None);
+    }
+}
@@ -0,0 +1,755 @@
+use crate::debouncer_instant::Debouncer;
+use crate::geyser_plugin_util::{setup_plugin, LoadOptions, PluginLoadError};
+use crate::latency_histograms::{LatencyHistograms, LatencySummary, PERCENTILES};
+use crate::metrics;
+use crate::plugin_host::PluginHostCrashed;
+use agave_geyser_plugin_interface::geyser_plugin_interface::{
+    GeyserPluginError, Result as PluginResult,
+};
+use libloading::Library;
+use log::{info, warn};
+use serde_derive::Serialize;
+use solana_geyser_plugin_manager::geyser_plugin_manager::LoadedGeyserPlugin;
+use std::collections::BTreeMap;
+use std::panic::AssertUnwindSafe;
+use std::path::Path;
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+
+// at most one log line per interval for the log policy
+const ERROR_LOG_INTERVAL: Duration = Duration::from_secs(1);
+
+// error kind of a call the child process of an isolated plugin crashed on
+pub const CRASH_KIND: &str = "crash";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Callback {
+    UpdateAccount,
+    UpdateSlotStatus,
+    NotifyTransaction,
+    NotifyEntry,
+    NotifyBlockMetadata,
+    NotifyEndOfStartup,
+}
+
+impl Callback {
+    pub const ALL: [Callback; 6] = [
+        Callback::UpdateAccount,
+        Callback::UpdateSlotStatus,
+        Callback::NotifyTransaction,
+        Callback::NotifyEntry,
+        Callback::NotifyBlockMetadata,
+        Callback::NotifyEndOfStartup,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Callback::UpdateAccount => "update_account",
+            Callback::UpdateSlotStatus => "update_slot_status",
+            Callback::NotifyTransaction => "notify_transaction",
+            Callback::NotifyEntry => "notify_entry",
+            Callback::NotifyBlockMetadata => "notify_block_metadata",
+            Callback::NotifyEndOfStartup => "notify_end_of_startup",
+        }
+    }
+}
+
+// the streams a plugin asked for; queried once after on_load like GeyserPluginService does
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct NotificationsEnabled {
+    pub accounts: bool,
+    pub transactions: bool,
+    pub entries: bool,
+}
+
+impl NotificationsEnabled {
+    fn of(plugin: &LoadedGeyserPlugin) -> Self {
+        Self {
+            accounts: plugin.account_data_notifications_enabled(),
+            transactions: plugin.transaction_notifications_enabled(),
+            entries: plugin.entry_notifications_enabled(),
+        }
+    }
+
+    fn union(self, other: Self) -> Self {
+        Self {
+            accounts: self.accounts || other.accounts,
+            transactions: self.transactions || other.transactions,
+            entries: self.entries || other.entries,
+        }
+    }
+
+    // account updates go to all plugins once any plugin enabled them (see AccountsUpdateNotifierImpl)
+    fn dispatched(&self, callback: Callback) -> bool {
+        match callback {
+            Callback::NotifyTransaction => self.transactions,
+            Callback::NotifyEntry => self.entries,
+            _ => true,
+        }
+    }
+
+    // slots and block metadata are always sent
+    pub fn active_streams(&self) -> Vec<&'static str> {
+        [
+            (self.accounts, "accounts"),
+            (self.transactions, "transactions"),
+            (self.entries, "entries"),
+            (true, "slots"),
+            (true, "block metadata"),
+        ]
+        .into_iter()
+        .filter(|(enabled, _)| *enabled)
+        .map(|(_, stream)| stream)
+        .collect()
+    }
+}
+
+// what to do if a plugin callback returns an error or panics; the validator logs and continues
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum ErrorPolicy {
+    Abort,
+    Log,
+    // abort once more than max_errors errors were counted
+    Count { max_errors: u64 },
+}
+
+impl FromStr for ErrorPolicy {
+    type Err = String;
+
+    // abort, log, count:1000
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "abort" => Ok(ErrorPolicy::Abort),
+            "log" => Ok(ErrorPolicy::Log),
+            _ => s
+                .strip_prefix("count:")
+                .and_then(|max_errors| max_errors.parse().ok())
+                .map(|max_errors| ErrorPolicy::Count { max_errors })
+                .ok_or_else(|| format!("expected abort, log or count:<max errors>: {}", s)),
+        }
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+#[error("plugin {plugin} failed in {}: {kind}: {message}", callback.as_str())]
+pub struct PluginCallError {
+    pub plugin: String,
+    pub callback: Callback,
+    pub kind: &'static str,
+    pub message: String,
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct ErrorCount {
+    pub count: u64,
+    pub last_message: String,
+}
+
+#[derive(Debug, Default, Clone, Copy)]
+pub struct CallbackTiming {
+    pub count: u64,
+    pub total: Duration,
+    pub max: Duration,
+}
+
+impl CallbackTiming {
+    fn record(&mut self, elapsed: Duration) {
+        self.count += 1;
+        self.total += elapsed;
+        self.max = self.max.max(elapsed);
+    }
+
+    pub fn avg(&self) -> Duration {
+        if self.count == 0 {
+            Duration::ZERO
+        } else {
+            self.total.div_f64(self.count as f64)
+        }
+    }
+}
+
+struct DispatchedPlugin {
+    // None after a failed reload
+    plugin: Option<LoadedGeyserPlugin>,
+    // the plugin code lives in it, so it is dropped after the plugin; None if linked into the binary
+    lib: Option<Library>,
+    name: String,
+    notifications: NotificationsEnabled,
+    // indexed by Callback
+    timings: [CallbackTiming; Callback::ALL.len()],
+    latencies: LatencyHistograms,
+    // indexed by Callback, by error kind
+    errors: [BTreeMap<&'static str, ErrorCount>; Callback::ALL.len()],
+}
+
+// calls all plugins sequentially in load order like the GeyserPluginManager on the replay thread
+pub struct PluginDispatcher {
+    plugins: Vec<DispatchedPlugin>,
+    error_policy: ErrorPolicy,
+    errors_total: u64,
+    error_log_debouncer: Debouncer,
+}
+
+impl DispatchedPlugin {
+    fn new(plugin: LoadedGeyserPlugin, lib: Option<Library>) -> Self {
+        let mut dispatched = DispatchedPlugin {
+            plugin: None,
+            lib: None,
+            name: String::new(),
+            notifications: NotificationsEnabled::default(),
+            timings: Default::default(),
+            latencies: LatencyHistograms::default(),
+            errors: Default::default(),
+        };
+        dispatched.set_plugin(plugin, lib);
+        dispatched
+    }
+
+    fn set_plugin(&mut self, plugin: LoadedGeyserPlugin, lib: Option<Library>) {
+        self.notifications = NotificationsEnabled::of(&plugin);
+        info!(
+            "Notifications enabled by plugin {}: {:?}",
+            plugin.name(),
+            self.notifications
+        );
+        self.name = plugin.name().to_string();
+        self.plugin = Some(plugin);
+        self.lib = lib;
+    }
+
+    // like GeyserPluginManager::unload
+    fn unload(&mut self) {
+        if let Some(mut plugin) = self.plugin.take() {
+            info!("Unloading plugin {}", plugin.name());
+            plugin.on_unload();
+        }
+        // the plugin is dropped now, its code can be unloaded
+        self.lib = None;
+        self.notifications = NotificationsEnabled::default();
+    }
+}
+
+impl PluginDispatcher {
+    pub fn new(
+        plugins: Vec<(LoadedGeyserPlugin, Option<Library>)>,
+        error_policy: ErrorPolicy,
+    ) -> Self {
+        let plugins = plugins
+            .into_iter()
+            .map(|(plugin, lib)| DispatchedPlugin::new(plugin, lib))
+            .collect();
+        Self {
+            plugins,
+            error_policy,
+            errors_total: 0,
+            error_log_debouncer: Debouncer::new(ERROR_LOG_INTERVAL),
+        }
+    }
+
+    pub fn notifications_enabled(&self) -> NotificationsEnabled {
+        self.plugins
+            .iter()
+            .fold(NotificationsEnabled::default(), |enabled, plugin| {
+                enabled.union(plugin.notifications)
+            })
+    }
+
+    // fails if the error policy stops the run; panics of the plugin are handled like errors;
+    // account_size buckets the latencies of update_account
+    pub fn dispatch(
+        &mut self,
+        callback: Callback,
+        account_size: Option<usize>,
+        call: impl Fn(&LoadedGeyserPlugin) -> PluginResult<()>,
+    ) -> Result<(), PluginCallError> {
+        for dispatched in self.plugins.iter_mut() {
+            if !dispatched.notifications.dispatched(callback) {
+                continue;
+            }
+            let Some(plugin) = &dispatched.plugin else {
+                continue;
+            };
+            let started_at = Instant::now();
+            let result = std::panic::catch_unwind(AssertUnwindSafe(|| call(plugin)));
+            let elapsed = started_at.elapsed();
+            let failure = match result {
+                Ok(Ok(())) => None,
+                Ok(Err(err)) => Some((error_kind(&err), err.to_string())),
+                Err(payload) => Some(("panic", panic_message(payload.as_ref()))),
+            };
+            // the restart of a crashed plugin host is not a call latency
+            if !matches!(failure, Some((CRASH_KIND, _))) {
+                dispatched.timings[callback as usize].record(elapsed);
+                dispatched.latencies.record(callback, account_size, elapsed);
+                metrics::plugin_called(&dispatched.name, callback, elapsed);
+            }
+
+            let Some((kind, message)) = failure else {
+                continue;
+            };
+            let error_count = dispatched.errors[callback as usize]
+                .entry(kind)
+                .or_default();
+            error_count.count += 1;
+            error_count.last_message.clone_from(&message);
+            self.errors_total += 1;
+
+            let error = PluginCallError {
+                plugin: dispatched.name.clone(),
+                callback,
+                kind,
+                message,
+            };
+            match self.error_policy {
+                ErrorPolicy::Abort => return Err(error),
+                ErrorPolicy::Count { max_errors } if self.errors_total > max_errors => {
+                    return Err(error)
+                }
+                ErrorPolicy::Count { .. } => {}
+                ErrorPolicy::Log => {
+                    if self.error_log_debouncer.can_fire() {
+                        warn!("{} ({} errors so far)", error, self.errors_total);
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    // like GeyserPluginManager::reload_plugin but the plugin keeps its position and timings;
+    // if loading fails the plugin stays unloaded
+    pub fn reload_plugin(
+        &mut self,
+        index: usize,
+        config_file: &Path,
+        options: LoadOptions,
+    ) -> Result<(), PluginLoadError> {
+        let dispatched = &mut self.plugins[index];
+        dispatched.unload();
+        let (plugin, lib) = setup_plugin(config_file, true, options)?;
+        dispatched.set_plugin(plugin, lib);
+        Ok(())
+    }
+
+    pub fn unload(mut self) {
+        for dispatched in self.plugins.iter_mut() {
+            dispatched.unload();
+        }
+    }
+
+    // (plugin name, callback, timing) in load order
+    pub fn timings(&self) -> Vec<(&str, Callback, CallbackTiming)> {
+        self.plugins
+            .iter()
+            .flat_map(|dispatched| {
+                Callback::ALL.iter().map(move |callback| {
+                    (
+                        dispatched.name.as_str(),
+                        *callback,
+                        dispatched.timings[*callback as usize],
+                    )
+                })
+            })
+            .filter(|(_, _, timing)| timing.count > 0)
+            .collect()
+    }
+
+    // in load order; kept for plugins which failed to reload
+    pub fn plugin_names(&self) -> Vec<&str> {
+        self.plugins
+            .iter()
+            .map(|dispatched| dispatched.name.as_str())
+            .collect()
+    }
+
+    // calls the child process of an isolated plugin crashed on, in load order
+    pub fn crashes(&self) -> Vec<u64> {
+        self.plugins
+            .iter()
+            .map(|dispatched| {
+                dispatched
+                    .errors
+                    .iter()
+                    .filter_map(|by_kind| by_kind.get(CRASH_KIND))
+                    .map(|error_count| error_count.count)
+                    .sum()
+            })
+            .collect()
+    }
+
+    // (plugin name, latency percentiles) in load order
+    pub fn latencies(&self) -> Vec<(&str, LatencySummary)> {
+        self.plugins
+            .iter()
+            .flat_map(|dispatched| {
+                dispatched
+                    .latencies
+                    .summaries()
+                    .into_iter()
+                    .map(|summary| (dispatched.name.as_str(), summary))
+            })
+            .collect()
+    }
+
+    // (plugin name, callback, error kind, count) in load order
+    pub fn errors(&self) -> Vec<(&str, Callback, &'static str, &ErrorCount)> {
+        self.plugins
+            .iter()
+            .flat_map(|dispatched| {
+                Callback::ALL.iter().flat_map(move |callback| {
+                    dispatched.errors[*callback as usize]
+                        .iter()
+                        .map(move |(kind, error_count)| {
+                            (dispatched.name.as_str(), *callback, *kind, error_count)
+                        })
+                })
+            })
+            .collect()
+    }
+
+    pub fn log_errors(&self) {
+        if self.errors_total == 0 {
+            return;
+        }
+        info!(
+            "plugin errors: {} (policy {:?})",
+            self.errors_total, self.error_policy
+        );
+        for (name, callback, kind, error_count) in self.errors() {
+            info!(
+                "  {} {} {}: count={} last={}",
+                name,
+                callback.as_str(),
+                kind,
+                error_count.count,
+                error_count.last_message
+            );
+        }
+    }
+
+    pub fn log_latencies(&self) {
+        for (index, (name, summary)) in self.latencies().into_iter().enumerate() {
+            if index == 0 {
+                info!("plugin latencies:");
+            }
+            let percentiles = PERCENTILES
+                .iter()
+                .zip(summary.percentiles)
+                .map(|((_, label), latency)| format!("{}={:?}", label, latency))
+                .collect::<Vec<_>>()
+                .join(" ");
+            info!(
+                "  {} {}{}: count={} {} max={:?}",
+                name,
+                summary.callback.as_str(),
+                summary
+                    .account_size
+                    .map(|size| format!(" ({})", size))
+                    .unwrap_or_default(),
+                summary.count,
+                percentiles,
+                summary.max
+            );
+        }
+    }
+
+    pub fn log_timings(&self) {
+        for (index, (name, callback, timing)) in self.timings().into_iter().enumerate() {
+            if index == 0 {
+                info!("plugin timings:");
+            }
+            info!(
+                "  {} {}: count={} avg={:?} max={:?} total={:?}",
+                name,
+                callback.as_str(),
+                timing.count,
+                timing.avg(),
+                timing.max,
+                timing.total
+            );
+        }
+    }
+}
+
+fn error_kind(err: &GeyserPluginError) -> &'static str {
+    match err {
+        GeyserPluginError::ConfigFileOpenError(_) => "config_file_open",
+        GeyserPluginError::ConfigFileReadError { .. } => "config_file_read",
+        GeyserPluginError::AccountsUpdateError { .. } => "accounts_update",
+        GeyserPluginError::SlotStatusUpdateError { .. } => "slot_status_update",
+        GeyserPluginError::Custom(err) if err.is::<PluginHostCrashed>() => CRASH_KIND,
+        GeyserPluginError::Custom(_) => "custom",
+        GeyserPluginError::TransactionUpdateError { .. } => "transaction_update",
+    }
+}
+
+fn panic_message(payload: &(dyn std::any::Any + Send)) -> String {
+    if let Some(message) = payload.downcast_ref::<&str>() {
+        message.to_string()
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        message.clone()
+    } else {
+        "unknown panic payload".to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::abi_check::AbiCheck;
+    use agave_geyser_plugin_interface::geyser_plugin_interface::GeyserPlugin;
+    use std::sync::{Arc, Mutex};
+
+    #[derive(Debug)]
+    struct OrderPlugin {
+        name: &'static str,
+        calls: Arc<Mutex<Vec<&'static str>>>,
+        entries: bool,
+    }
+
+    impl GeyserPlugin for OrderPlugin {
+        fn name(&self) -> &'static str {
+            self.name
+        }
+
+        fn on_unload(&mut self) {
+            self.calls.lock().unwrap().push(self.name);
+        }
+
+        fn notify_end_of_startup(&self) -> PluginResult<()> {
+            self.calls.lock().unwrap().push(self.name);
+            Ok(())
+        }
+
+        fn entry_notifications_enabled(&self) -> bool {
+            self.entries
+        }
+    }
+
+    #[test]
+    fn dispatch_and_unload_in_order() {
+        let calls = Arc::new(Mutex::new(vec![]));
+        let plugins = [("first", false), ("second", true)]
+            .into_iter()
+            .map(|(name, entries)| {
+                let plugin = LoadedGeyserPlugin::new(
+                    Box::new(OrderPlugin {
+                        name,
+                        calls: calls.clone(),
+                        entries,
+                    }),
+                    None,
+                );
+                (plugin, None)
+            })
+            .collect();
+        let mut dispatcher = PluginDispatcher::new(plugins, ErrorPolicy::Abort);
+
+        dispatcher
+            .dispatch(Callback::NotifyEndOfStartup, None, |plugin| {
+                plugin.notify_end_of_startup()
+            })
+            .unwrap();
+        dispatcher
+            .dispatch(Callback::NotifyEntry, None, |plugin| {
+                plugin.notify_end_of_startup()
+            })
+            .unwrap();
+        dispatcher
+            .dispatch(Callback::NotifyTransaction, None, |plugin| {
+                plugin.notify_end_of_startup()
+            })
+            .unwrap();
+
+        // entries only go to the plugin which enabled them, transactions to none
+        assert_eq!(*calls.lock().unwrap(), vec!["first", "second", "second"]);
+        assert_eq!(
+            dispatcher.notifications_enabled(),
+            NotificationsEnabled {
+                accounts: true,
+                transactions: false,
+                entries: true,
+            }
+        );
+        assert_eq!(
+            dispatcher.notifications_enabled().active_streams(),
+            vec!["accounts", "entries", "slots", "block metadata"]
+        );
+
+        let timings = dispatcher.timings();
+        assert_eq!(timings.len(), 3);
+        assert_eq!(timings[0].0, "first");
+        assert_eq!(timings[0].1, Callback::NotifyEndOfStartup);
+        assert_eq!(timings[0].2.count, 1);
+        assert_eq!(timings[1].0, "second");
+        assert_eq!(timings[1].1, Callback::NotifyEntry);
+
+        calls.lock().unwrap().clear();
+        dispatcher.unload();
+        assert_eq!(*calls.lock().unwrap(), vec!["first", "second"]);
+    }
+
+    #[test]
+    fn failed_reload_keeps_plugin_unloaded() {
+        let calls = Arc::new(Mutex::new(vec![]));
+        let plugin = LoadedGeyserPlugin::new(
+            Box::new(OrderPlugin {
+                name: "reloaded",
+                calls: calls.clone(),
+                entries: true,
+            }),
+            None,
+        );
+        let mut dispatcher = PluginDispatcher::new(vec![(plugin, None)], ErrorPolicy::Abort);
+        dispatcher
+            .dispatch(Callback::NotifyEndOfStartup, None, |plugin| {
+                plugin.notify_end_of_startup()
+            })
+            .unwrap();
+
+        let err = dispatcher
+            .reload_plugin(
+                0,
+                Path::new("/nonexistent/reload.json"),
+                LoadOptions {
+                    abi_check: AbiCheck::Strict,
+                    isolate: false,
+                },
+            )
+            .unwrap_err();
+        assert!(matches!(err, PluginLoadError::ConfigUnreadable { .. }));
+        // on_unload was called before loading the new library
+        assert_eq!(*calls.lock().unwrap(), vec!["reloaded", "reloaded"]);
+
+        dispatcher
+            .dispatch(Callback::NotifyEndOfStartup, None, |plugin| {
+                plugin.notify_end_of_startup()
+            })
+            .unwrap();
+        assert_eq!(calls.lock().unwrap().len(), 2);
+        assert_eq!(
+            dispatcher.notifications_enabled(),
+            NotificationsEnabled::default()
+        );
+        // timings survive the reload
+        assert_eq!(dispatcher.timings()[0].2.count, 1);
+
+        dispatcher.unload();
+        assert_eq!(calls.lock().unwrap().len(), 2);
+    }
+
+    #[derive(Debug)]
+    struct FailingPlugin;
+
+    impl GeyserPlugin for FailingPlugin {
+        fn name(&self) -> &'static str {
+            "failing"
+        }
+
+        fn update_slot_status(
+            &self,
+            slot: u64,
+            _parent: Option<u64>,
+            _status: agave_geyser_plugin_interface::geyser_plugin_interface::SlotStatus,
+        ) -> PluginResult<()> {
+            Err(GeyserPluginError::SlotStatusUpdateError {
+                msg: format!("rejected slot {}", slot),
+            })
+        }
+
+        fn notify_end_of_startup(&self) -> PluginResult<()> {
+            panic!("startup failed");
+        }
+    }
+
+    fn failing_dispatcher(error_policy: ErrorPolicy) -> PluginDispatcher {
+        let plugin = LoadedGeyserPlugin::new(Box::new(FailingPlugin), None);
+        PluginDispatcher::new(vec![(plugin, None)], error_policy)
+    }
+
+    fn update_slot(dispatcher: &mut PluginDispatcher, slot: u64) -> Result<(), PluginCallError> {
+        dispatcher.dispatch(Callback::UpdateSlotStatus, None, |plugin| {
+            plugin.update_slot_status(
+                slot,
+                None,
+                agave_geyser_plugin_interface::geyser_plugin_interface::SlotStatus::Confirmed,
+            )
+        })
+    }
+
+    #[test]
+    fn error_policies() {
+        assert_eq!("abort".parse(), Ok(ErrorPolicy::Abort));
+        assert_eq!("log".parse(), Ok(ErrorPolicy::Log));
+        assert_eq!(
+            "count:100".parse(),
+            Ok(ErrorPolicy::Count { max_errors: 100 })
+        );
+        assert!("count:".parse::<ErrorPolicy>().is_err());
+
+        let mut dispatcher = failing_dispatcher(ErrorPolicy::Abort);
+        let err = update_slot(&mut dispatcher, 1).unwrap_err();
+        assert_eq!(err.kind, "slot_status_update");
+        assert_eq!(err.callback, Callback::UpdateSlotStatus);
+
+        let mut dispatcher = failing_dispatcher(ErrorPolicy::Count { max_errors: 2 });
+        assert!(update_slot(&mut dispatcher, 1).is_ok());
+        assert!(update_slot(&mut dispatcher, 2).is_ok());
+        assert!(update_slot(&mut dispatcher, 3).is_err());
+
+        let mut dispatcher = failing_dispatcher(ErrorPolicy::Log);
+        for slot in 0..5 {
+            assert!(update_slot(&mut dispatcher, slot).is_ok());
+        }
+        // panics are caught and counted like errors
+        assert!(dispatcher
+            .dispatch(Callback::NotifyEndOfStartup, None, |plugin| {
+                plugin.notify_end_of_startup()
+            })
+            .is_ok());
+
+        let errors = dispatcher.errors();
+        assert_eq!(errors.len(), 2);
+        assert_eq!(
+            (errors[0].1, errors[0].2, errors[0].3.count),
+            (Callback::UpdateSlotStatus, "slot_status_update", 5)
+        );
+        assert!(errors[0].3.last_message.contains("rejected slot 4"));
+        assert_eq!(
+            (errors[1].1, errors[1].2, errors[1].3.count),
+            (Callback::NotifyEndOfStartup, "panic", 1)
+        );
+        assert_eq!(errors[1].3.last_message, "startup failed");
+    }
+
+    #[test]
+    fn crashes_are_counted_but_not_timed() {
+        let mut dispatcher = failing_dispatcher(ErrorPolicy::Log);
+        let crashed = || PluginHostCrashed {
+            exit: "signal: 11 (SIGSEGV)".to_string(),
+            request: "notify_end_of_startup".to_string(),
+        };
+        assert!(dispatcher
+            .dispatch(Callback::NotifyEndOfStartup, None, |_| {
+                Err(GeyserPluginError::Custom(Box::new(crashed())))
+            })
+            .is_ok());
+
+        let errors = dispatcher.errors();
+        assert_eq!(
+            (errors[0].1, errors[0].2, errors[0].3.count),
+            (Callback::NotifyEndOfStartup, CRASH_KIND, 1)
+        );
+        assert!(errors[0].3.last_message.contains("SIGSEGV"));
+        assert_eq!(dispatcher.crashes(), vec![1]);
+        assert!(dispatcher.latencies().is_empty());
+
+        let mut dispatcher = failing_dispatcher(ErrorPolicy::Abort);
+        let err = dispatcher
+            .dispatch(Callback::NotifyEndOfStartup, None, |_| {
+                Err(GeyserPluginError::Custom(Box::new(crashed())))
+            })
+            .unwrap_err();
+        assert_eq!(err.kind, CRASH_KIND);
+    }
+}
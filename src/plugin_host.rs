@@ -0,0 +1,555 @@
+use crate::abi_check::AbiCheck;
+use crate::geyser_plugin_util::{notify_plugin, setup_plugin, LoadOptions, PluginLoadError};
+use crate::replay::mock_message_from_recorded;
+use agave_geyser_plugin_interface::geyser_plugin_interface::{
+    GeyserPlugin, GeyserPluginError, ReplicaAccountInfoVersions, ReplicaBlockInfoVersions,
+    ReplicaEntryInfoVersions, ReplicaTransactionInfoVersions, Result as PluginResult, SlotStatus,
+};
+use clap::ValueEnum;
+use geyser_recorder::recording::{
+    read_frame, write_frame, RecordedAccount, RecordedBlockMeta, RecordedEntry, RecordedKind,
+    RecordedSlot, RecordedTransaction,
+};
+use log::{error, info, warn};
+use serde_derive::{Deserialize, Serialize};
+use solana_sdk::clock::Slot;
+use solana_sdk::reserved_account_keys::ReservedAccountKeys;
+use std::io::{BufReader, BufWriter, ErrorKind, Write};
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, ExitStatus};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tokio::signal::unix::{signal, SignalKind};
+
+// the child process must connect within this time; loading the plugin comes after
+const HOST_CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+
+static HOST_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+// mock -> plugin host; every request gets exactly one response
+#[derive(Serialize, Deserialize)]
+enum HostRequest {
+    Load { is_reload: bool },
+    Notify(RecordedKind),
+    Unload,
+}
+
+#[derive(Serialize, Deserialize)]
+enum HostResponse {
+    Loaded(HostedPlugin),
+    LoadFailed(PluginLoadError),
+    Notified(Result<(), String>),
+    Unloaded,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct HostedPlugin {
+    name: String,
+    account_data_notifications_enabled: bool,
+    transaction_notifications_enabled: bool,
+    entry_notifications_enabled: bool,
+}
+
+// entry point of the child process (--plugin-host); returns the exit code
+pub fn run_plugin_host(socket_path: &Path, config_file: &Path, abi_check: AbiCheck) -> i32 {
+    // the mock decides when to unload; a ctrl-c in the terminal reaches the whole process group
+    let _ignored_signals =
+        [SignalKind::interrupt(), SignalKind::terminate()].map(|kind| signal(kind).unwrap());
+
+    let stream = UnixStream::connect(socket_path).unwrap();
+    let mut reader = BufReader::new(stream.try_clone().unwrap());
+    let mut writer = BufWriter::new(stream);
+
+    let Ok(Some(HostRequest::Load { is_reload })) = read_frame(&mut reader) else {
+        error!("plugin host expected a load request");
+        return 1;
+    };
+    let options = LoadOptions {
+        abi_check,
+        isolate: false,
+    };
+    let (mut plugin, lib) = match setup_plugin(config_file, is_reload, options) {
+        Ok(loaded) => loaded,
+        Err(err) => {
+            let exit_code = err.exit_code();
+            let _ = respond(&mut writer, &HostResponse::LoadFailed(err));
+            return exit_code;
+        }
+    };
+    let hosted = HostedPlugin {
+        name: plugin.name().to_string(),
+        account_data_notifications_enabled: plugin.account_data_notifications_enabled(),
+        transaction_notifications_enabled: plugin.transaction_notifications_enabled(),
+        entry_notifications_enabled: plugin.entry_notifications_enabled(),
+    };
+    if respond(&mut writer, &HostResponse::Loaded(hosted)).is_err() {
+        return 1;
+    }
+
+    let reserved_account_keys = ReservedAccountKeys::new_all_activated().active;
+    loop {
+        let request = match read_frame(&mut reader) {
+            Ok(Some(request)) => request,
+            Ok(None) | Err(_) => {
+                warn!("plugin host lost the connection to the mock - unloading");
+                break;
+            }
+        };
+        let response = match request {
+            HostRequest::Notify(kind) => {
+                let result = match mock_message_from_recorded(kind, &reserved_account_keys) {
+                    Some(message) => {
+                        notify_plugin(plugin.as_ref(), &message).map_err(|err| err.to_string())
+                    }
+                    None => Err("cannot rebuild the message".to_string()),
+                };
+                HostResponse::Notified(result)
+            }
+            HostRequest::Unload => break,
+            HostRequest::Load { .. } => {
+                error!("plugin host got a second load request");
+                return 1;
+            }
+        };
+        if respond(&mut writer, &response).is_err() {
+            warn!("plugin host lost the connection to the mock - unloading");
+            break;
+        }
+    }
+
+    plugin.on_unload();
+    // the plugin code lives in the library
+    drop(plugin);
+    drop(lib);
+    let _ = respond(&mut writer, &HostResponse::Unloaded);
+    0
+}
+
+fn respond(writer: &mut BufWriter<UnixStream>, response: &HostResponse) -> bincode::Result<()> {
+    write_frame(writer, response)?;
+    writer.flush()?;
+    Ok(())
+}
+
+#[derive(Debug)]
+struct PluginHost {
+    child: Child,
+    reader: BufReader<UnixStream>,
+    writer: BufWriter<UnixStream>,
+}
+
+impl PluginHost {
+    fn spawn(
+        config_file: &Path,
+        is_reload: bool,
+        abi_check: AbiCheck,
+    ) -> Result<(Self, HostedPlugin), PluginLoadError> {
+        // the plugin name is not known yet
+        let host_failed = |msg: String| PluginLoadError::PluginHostFailed {
+            plugin: config_file.display().to_string(),
+            msg,
+        };
+
+        let socket_path = std::env::temp_dir().join(format!(
+            "geyser-mock-{}-{}.sock",
+            std::process::id(),
+            HOST_COUNTER.fetch_add(1, Ordering::Relaxed)
+        ));
+        let listener =
+            UnixListener::bind(&socket_path).map_err(|err| host_failed(err.to_string()))?;
+        let connected = std::env::current_exe()
+            .and_then(|mock_exe| {
+                Command::new(mock_exe)
+                    .arg("--geyser-plugin-config")
+                    .arg(config_file)
+                    .arg("--abi-check")
+                    .arg(abi_check.to_possible_value().unwrap().get_name())
+                    .arg("--plugin-host")
+                    .arg(&socket_path)
+                    .spawn()
+            })
+            .and_then(|mut child| {
+                let accepted = accept_host(&listener, &mut child);
+                accepted.map(|stream| (child, stream))
+            });
+        let _ = std::fs::remove_file(&socket_path);
+        let (child, stream) = connected.map_err(|err| host_failed(err.to_string()))?;
+
+        let mut host = PluginHost {
+            child,
+            reader: BufReader::new(stream.try_clone().unwrap()),
+            writer: BufWriter::new(stream),
+        };
+        match host.request(&HostRequest::Load { is_reload }) {
+            Ok(HostResponse::Loaded(hosted)) => Ok((host, hosted)),
+            Ok(HostResponse::LoadFailed(err)) => {
+                host.kill();
+                Err(err)
+            }
+            Ok(_) => {
+                host.kill();
+                Err(host_failed("unexpected response to load".to_string()))
+            }
+            Err(_) => Err(host_failed(format!(
+                "{} while loading the plugin",
+                host.kill()
+            ))),
+        }
+    }
+
+    fn request(&mut self, request: &HostRequest) -> bincode::Result<HostResponse> {
+        write_frame(&mut self.writer, request)?;
+        self.writer.flush()?;
+        read_frame(&mut self.reader)?.ok_or_else(|| {
+            std::io::Error::new(
+                ErrorKind::UnexpectedEof,
+                "plugin host closed the connection",
+            )
+            .into()
+        })
+    }
+
+    // describes how the child exited
+    fn kill(&mut self) -> String {
+        let _ = self.child.kill();
+        match self.child.wait() {
+            Ok(status) => describe_exit(status),
+            Err(err) => err.to_string(),
+        }
+    }
+}
+
+impl Drop for PluginHost {
+    fn drop(&mut self) {
+        self.kill();
+    }
+}
+
+fn accept_host(listener: &UnixListener, child: &mut Child) -> std::io::Result<UnixStream> {
+    listener.set_nonblocking(true)?;
+    let started_at = Instant::now();
+    loop {
+        match listener.accept() {
+            Ok((stream, _)) => {
+                stream.set_nonblocking(false)?;
+                return Ok(stream);
+            }
+            Err(err) if err.kind() == ErrorKind::WouldBlock => {}
+            Err(err) => return Err(err),
+        }
+        if let Some(status) = child.try_wait()? {
+            return Err(std::io::Error::new(
+                ErrorKind::Other,
+                format!("exited before connecting: {}", describe_exit(status)),
+            ));
+        }
+        if started_at.elapsed() > HOST_CONNECT_TIMEOUT {
+            let _ = child.kill();
+            let _ = child.wait();
+            return Err(std::io::Error::new(
+                ErrorKind::TimedOut,
+                "did not connect in time",
+            ));
+        }
+        std::thread::sleep(Duration::from_millis(10));
+    }
+}
+
+// e.g. "signal: 11 (SIGSEGV) (core dumped)"
+fn describe_exit(status: ExitStatus) -> String {
+    // rust exits with 101 on panic
+    if status.code() == Some(101) {
+        format!("{} (panic)", status)
+    } else {
+        status.to_string()
+    }
+}
+
+fn describe_request(request: &HostRequest) -> String {
+    let HostRequest::Notify(kind) = request else {
+        return "no message".to_string();
+    };
+    match kind {
+        RecordedKind::Account(account) => format!(
+            "update_account {} of slot {} ({} bytes)",
+            account.pubkey,
+            account.slot,
+            account.data.len()
+        ),
+        RecordedKind::Slot(slot) => {
+            format!("update_slot_status {} {:?}", slot.slot, slot.status)
+        }
+        RecordedKind::Transaction(transaction) => format!(
+            "notify_transaction {} of slot {}",
+            transaction.signature, transaction.slot
+        ),
+        RecordedKind::Entry(entry) => {
+            format!("notify_entry {} of slot {}", entry.index, entry.slot)
+        }
+        RecordedKind::BlockMeta(block_meta) => {
+            format!("notify_block_metadata of slot {}", block_meta.slot)
+        }
+        RecordedKind::EndOfStartup => "notify_end_of_startup".to_string(),
+    }
+}
+
+// returned for the call the child process crashed on, so the error policy and the report see it
+#[derive(Debug, thiserror::Error)]
+#[error("plugin host crashed ({exit}), lost {request}")]
+pub struct PluginHostCrashed {
+    pub exit: String,
+    pub request: String,
+}
+
+// forwards all calls to the plugin running in a child process; if the child crashes it is
+// restarted and the message it crashed on is lost
+#[derive(Debug)]
+pub struct RemotePlugin {
+    config_file: PathBuf,
+    abi_check: AbiCheck,
+    name: &'static str,
+    hosted: HostedPlugin,
+    // None if the restart failed
+    host: Mutex<Option<PluginHost>>,
+    crashes: AtomicU64,
+    // a restarted plugin host is told again, it would stay in startup mode otherwise
+    end_of_startup_sent: AtomicBool,
+}
+
+pub fn spawn_plugin_host(
+    config_file: &Path,
+    is_reload: bool,
+    abi_check: AbiCheck,
+) -> Result<RemotePlugin, PluginLoadError> {
+    let (host, hosted) = PluginHost::spawn(config_file, is_reload, abi_check)?;
+    Ok(RemotePlugin {
+        config_file: config_file.to_path_buf(),
+        abi_check,
+        // name() must be static; leaks once per load
+        name: Box::leak(hosted.name.clone().into_boxed_str()),
+        hosted,
+        host: Mutex::new(Some(host)),
+        crashes: AtomicU64::new(0),
+        end_of_startup_sent: AtomicBool::new(false),
+    })
+}
+
+impl RemotePlugin {
+    fn notify(&self, kind: RecordedKind) -> PluginResult<()> {
+        let mut host = self.host.lock().unwrap();
+        let Some(running) = host.as_mut() else {
+            return Err(GeyserPluginError::Custom(
+                format!("plugin host of {} is not running", self.name).into(),
+            ));
+        };
+
+        if matches!(kind, RecordedKind::EndOfStartup) {
+            // also if the plugin crashes on it
+            self.end_of_startup_sent.store(true, Ordering::Relaxed);
+        }
+        let request = HostRequest::Notify(kind);
+        match running.request(&request) {
+            Ok(HostResponse::Notified(result)) => {
+                return result.map_err(|msg| GeyserPluginError::Custom(msg.into()))
+            }
+            Ok(_) => error!("unexpected response from plugin host of {}", self.name),
+            Err(_) => {}
+        }
+
+        let crashed = PluginHostCrashed {
+            exit: running.kill(),
+            request: describe_request(&request),
+        };
+        let crashes = self.crashes.fetch_add(1, Ordering::Relaxed) + 1;
+        error!(
+            "plugin host of {} crashed ({}) while dispatching {} - restarting (crash #{})",
+            self.name, crashed.exit, crashed.request, crashes
+        );
+        *host = self.restart();
+        Err(GeyserPluginError::Custom(Box::new(crashed)))
+    }
+
+    // None if the plugin cannot be loaded again
+    fn restart(&self) -> Option<PluginHost> {
+        let mut restarted = match PluginHost::spawn(&self.config_file, false, self.abi_check) {
+            Ok((restarted, _)) => restarted,
+            Err(err) => {
+                error!("cannot restart plugin host of {}: {}", self.name, err);
+                return None;
+            }
+        };
+        if !self.end_of_startup_sent.load(Ordering::Relaxed) {
+            return Some(restarted);
+        }
+        match restarted.request(&HostRequest::Notify(RecordedKind::EndOfStartup)) {
+            Ok(HostResponse::Notified(Ok(()))) => Some(restarted),
+            Ok(HostResponse::Notified(Err(msg))) => {
+                warn!(
+                    "restarted plugin host of {} failed in notify_end_of_startup: {}",
+                    self.name, msg
+                );
+                Some(restarted)
+            }
+            _ => {
+                error!(
+                    "restarted plugin host of {} crashed in notify_end_of_startup ({})",
+                    self.name,
+                    restarted.kill()
+                );
+                None
+            }
+        }
+    }
+}
+
+impl GeyserPlugin for RemotePlugin {
+    fn name(&self) -> &'static str {
+        self.name
+    }
+
+    fn on_unload(&mut self) {
+        let Some(mut host) = self.host.lock().unwrap().take() else {
+            return;
+        };
+        match host.request(&HostRequest::Unload) {
+            Ok(HostResponse::Unloaded) => {
+                let _ = host.child.wait();
+            }
+            _ => error!(
+                "plugin host of {} failed to unload ({})",
+                self.name,
+                host.kill()
+            ),
+        }
+        info!(
+            "plugin host of {} stopped after {} crashes",
+            self.name,
+            self.crashes.load(Ordering::Relaxed)
+        );
+    }
+
+    fn update_account(
+        &self,
+        account: ReplicaAccountInfoVersions,
+        slot: Slot,
+        is_startup: bool,
+    ) -> PluginResult<()> {
+        let ReplicaAccountInfoVersions::V0_0_3(account) = account else {
+            return Ok(());
+        };
+        self.notify(RecordedKind::Account(RecordedAccount::from_replica(
+            account, slot, is_startup,
+        )))
+    }
+
+    fn notify_end_of_startup(&self) -> PluginResult<()> {
+        self.notify(RecordedKind::EndOfStartup)
+    }
+
+    fn update_slot_status(
+        &self,
+        slot: Slot,
+        parent: Option<u64>,
+        status: SlotStatus,
+    ) -> PluginResult<()> {
+        self.notify(RecordedKind::Slot(RecordedSlot {
+            slot,
+            parent,
+            status: status.into(),
+        }))
+    }
+
+    fn notify_transaction(
+        &self,
+        transaction: ReplicaTransactionInfoVersions,
+        slot: Slot,
+    ) -> PluginResult<()> {
+        let ReplicaTransactionInfoVersions::V0_0_2(transaction) = transaction else {
+            return Ok(());
+        };
+        let transaction = RecordedTransaction::from_replica(transaction, slot)
+            .map_err(|msg| GeyserPluginError::TransactionUpdateError { msg })?;
+        self.notify(RecordedKind::Transaction(Box::new(transaction)))
+    }
+
+    fn notify_entry(&self, entry: ReplicaEntryInfoVersions) -> PluginResult<()> {
+        let ReplicaEntryInfoVersions::V0_0_2(entry) = entry else {
+            return Ok(());
+        };
+        self.notify(RecordedKind::Entry(RecordedEntry::from_replica(entry)))
+    }
+
+    fn notify_block_metadata(&self, blockinfo: ReplicaBlockInfoVersions) -> PluginResult<()> {
+        let ReplicaBlockInfoVersions::V0_0_4(blockinfo) = blockinfo else {
+            return Ok(());
+        };
+        self.notify(RecordedKind::BlockMeta(RecordedBlockMeta::from_replica(
+            blockinfo,
+        )))
+    }
+
+    fn account_data_notifications_enabled(&self) -> bool {
+        self.hosted.account_data_notifications_enabled
+    }
+
+    fn transaction_notifications_enabled(&self) -> bool {
+        self.hosted.transaction_notifications_enabled
+    }
+
+    fn entry_notifications_enabled(&self) -> bool {
+        self.hosted.entry_notifications_enabled
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use geyser_recorder::recording::RecordedSlotStatus;
+    use std::os::unix::process::ExitStatusExt;
+
+    #[test]
+    fn describe_crash() {
+        assert_eq!(
+            describe_exit(ExitStatus::from_raw(11)),
+            "signal: 11 (SIGSEGV)"
+        );
+        assert_eq!(
+            describe_exit(ExitStatus::from_raw(101 << 8)),
+            "exit status: 101 (panic)"
+        );
+
+        let request = HostRequest::Notify(RecordedKind::Slot(RecordedSlot {
+            slot: 42,
+            parent: Some(41),
+            status: RecordedSlotStatus::Rooted,
+        }));
+        assert_eq!(describe_request(&request), "update_slot_status 42 Rooted");
+    }
+
+    #[test]
+    fn requests_roundtrip_over_socket() {
+        let (mock, host) = UnixStream::pair().unwrap();
+        let mut writer = BufWriter::new(mock);
+        write_frame(&mut writer, &HostRequest::Load { is_reload: true }).unwrap();
+        write_frame(
+            &mut writer,
+            &HostRequest::Notify(RecordedKind::EndOfStartup),
+        )
+        .unwrap();
+        writer.flush().unwrap();
+        drop(writer);
+
+        let mut reader = BufReader::new(host);
+        assert!(matches!(
+            read_frame(&mut reader).unwrap(),
+            Some(HostRequest::Load { is_reload: true })
+        ));
+        assert!(matches!(
+            read_frame(&mut reader).unwrap(),
+            Some(HostRequest::Notify(RecordedKind::EndOfStartup))
+        ));
+        assert!(read_frame::<HostRequest>(&mut reader).unwrap().is_none());
+    }
+}
@@ -0,0 +1,143 @@
+use crate::geyser_plugin_util::{
+    MockAccount, MockBlockMeta, MockEntry, MockMessage, MockSlot, MockTransaction,
+};
+use crate::message_queue::MessageSender;
+use crate::metrics;
+use crate::pacing::Pacing;
+use geyser_recorder::recording::{RecordedKind, RecordedTransaction, RecordingReader};
+use log::{error, info, warn};
+use solana_sdk::message::SimpleAddressLoader;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::reserved_account_keys::ReservedAccountKeys;
+use solana_sdk::transaction::{MessageHash, SanitizedTransaction};
+use solana_transaction_status::TransactionStatusMeta;
+use std::collections::HashSet;
+use std::time::Duration;
+use tokio::time::Instant;
+
+// feeds a recording of the geyser-recorder plugin with the original relative timing (scaled by pacing); blocks if the plugin is slow;
+// fails if the recording cannot be read to the end
+pub async fn replay_recording(
+    geyser_channel: MessageSender,
+    recording_file: String,
+    mut reader: RecordingReader,
+    pacing: Pacing,
+) -> bincode::Result<()> {
+    info!(
+        "Replaying geyser recording from {} with pacing {:?}",
+        recording_file, pacing
+    );
+
+    let started_at = Instant::now();
+    let mut first_elapsed_us = None;
+    let mut events_sent: u64 = 0;
+    // like the validator, so that writable flags match the recording
+    let reserved_account_keys = ReservedAccountKeys::new_all_activated().active;
+
+    while let Some(event) = reader.next_event()? {
+        // timing is relative to the first recorded call
+        let first_elapsed_us = *first_elapsed_us.get_or_insert(event.elapsed_us);
+        let offset = Duration::from_micros(event.elapsed_us.saturating_sub(first_elapsed_us));
+        pacing.sleep_until(started_at, offset).await;
+
+        let Some(message) = mock_message_from_recorded(event.kind, &reserved_account_keys) else {
+            continue;
+        };
+        metrics::message_generated(&message);
+        if geyser_channel.send(message).await.is_err() {
+            error!("channel was closed - shutting down");
+            return Ok(());
+        }
+        events_sent += 1;
+    }
+
+    info!(
+        "Replay finished after {} events in {:.3}s",
+        events_sent,
+        started_at.elapsed().as_secs_f64()
+    );
+    Ok(())
+}
+
+pub fn mock_message_from_recorded(
+    kind: RecordedKind,
+    reserved_account_keys: &HashSet<Pubkey>,
+) -> Option<MockMessage> {
+    let message = match kind {
+        RecordedKind::Account(account) => MockMessage::Account(MockAccount {
+            slot: account.slot,
+            pubkey: account.pubkey,
+            lamports: account.lamports,
+            data: account.data,
+            owner: account.owner,
+            executable: account.executable,
+            rent_epoch: account.rent_epoch,
+            write_version: account.write_version,
+            is_startup: account.is_startup,
+        }),
+        RecordedKind::Slot(slot) => MockMessage::Slot(MockSlot {
+            slot: slot.slot,
+            parent: slot.parent,
+            status: slot.status.into(),
+        }),
+        RecordedKind::Transaction(transaction) => MockMessage::Transaction(Box::new(
+            mock_transaction_from_recorded(*transaction, reserved_account_keys)?,
+        )),
+        RecordedKind::Entry(entry) => MockMessage::Entry(MockEntry {
+            slot: entry.slot,
+            index: entry.index,
+            num_hashes: entry.num_hashes,
+            hash: entry.hash,
+            executed_transaction_count: entry.executed_transaction_count,
+            starting_transaction_index: entry.starting_transaction_index,
+        }),
+        RecordedKind::BlockMeta(block_meta) => MockMessage::BlockMeta(MockBlockMeta {
+            slot: block_meta.slot,
+            parent_slot: block_meta.parent_slot,
+            parent_blockhash: block_meta.parent_blockhash,
+            blockhash: block_meta.blockhash,
+            rewards: block_meta.rewards,
+            block_time: block_meta.block_time,
+            block_height: block_meta.block_height,
+            executed_transaction_count: block_meta.executed_transaction_count,
+            entry_count: block_meta.entry_count,
+        }),
+        RecordedKind::EndOfStartup => MockMessage::EndOfStartup,
+    };
+    Some(message)
+}
+
+fn mock_transaction_from_recorded(
+    recorded: RecordedTransaction,
+    reserved_account_keys: &HashSet<Pubkey>,
+) -> Option<MockTransaction> {
+    let transaction = match SanitizedTransaction::try_create(
+        recorded.transaction,
+        MessageHash::Precomputed(recorded.message_hash),
+        Some(recorded.is_vote),
+        SimpleAddressLoader::Enabled(recorded.loaded_addresses.clone()),
+        reserved_account_keys,
+    ) {
+        Ok(transaction) => transaction,
+        Err(err) => {
+            warn!(
+                "skipping recorded transaction {}: {}",
+                recorded.signature, err
+            );
+            return None;
+        }
+    };
+
+    let mut transaction_status_meta = TransactionStatusMeta::from(recorded.transaction_status_meta);
+    // not part of the stored meta
+    transaction_status_meta.loaded_addresses = recorded.loaded_addresses;
+
+    Some(MockTransaction {
+        slot: recorded.slot,
+        signature: recorded.signature,
+        is_vote: recorded.is_vote,
+        transaction,
+        transaction_status_meta,
+        index: recorded.index,
+    })
+}
@@ -0,0 +1,298 @@
+use crate::geyser_plugin_util::{plugin_build_info, MockMessage, PluginBuildInfo};
+use crate::latency_histograms::LatencySummary;
+use crate::metrics;
+use crate::plugin_dispatcher::PluginDispatcher;
+use crate::Args;
+use agave_geyser_plugin_interface::geyser_plugin_interface::SlotStatus;
+use serde_derive::Serialize;
+use std::collections::BTreeMap;
+use std::io::Write;
+use std::path::Path;
+use std::time::Duration;
+
+// collected by the receive loop
+#[derive(Debug, Default)]
+pub struct DispatchStats {
+    // by MockMessage::kind
+    pub dispatched: BTreeMap<&'static str, u64>,
+    pub account_bytes_dispatched: u64,
+    // slots which were processed, i.e. without the commitment updates
+    pub slots_processed: u64,
+    pub max_channel_depth: usize,
+    pub max_channel_bytes: usize,
+    // --backpressure fail-run stopped the traffic
+    pub overflowed: bool,
+    // the --replay recording could not be read to the end
+    pub replay_failed: bool,
+    pub reload_failures: Vec<ReloadFailure>,
+}
+
+// a plugin which failed to reload on SIGHUP and stayed unloaded
+#[derive(Debug, Clone, Serialize)]
+pub struct ReloadFailure {
+    pub config_file: String,
+    pub error: String,
+    // see PluginLoadError::exit_code
+    pub exit_code: i32,
+}
+
+impl DispatchStats {
+    pub fn record(&mut self, message: &MockMessage, channel_len: usize, channel_bytes: usize) {
+        *self.dispatched.entry(message.kind()).or_default() += 1;
+        if let Some(data_len) = message.account_data_len() {
+            self.account_bytes_dispatched += data_len as u64;
+        }
+        if let MockMessage::Slot(mock_slot) = message {
+            if mock_slot.status == SlotStatus::Processed {
+                self.slots_processed += 1;
+            }
+        }
+        self.max_channel_depth = self.max_channel_depth.max(channel_len);
+        self.max_channel_bytes = self.max_channel_bytes.max(channel_bytes);
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct RunReport<'a> {
+    pub args: &'a Args,
+    // rfc3339
+    pub started_at: String,
+    pub duration_secs: f64,
+    // stopped by --plugin-error-policy
+    pub aborted: bool,
+    // stopped by --backpressure fail-run
+    pub overflowed: bool,
+    // the --replay recording could not be read to the end
+    pub replay_failed: bool,
+    pub plugins: Vec<PluginReport>,
+    // by MockMessage::kind
+    pub messages: BTreeMap<&'static str, MessageReport>,
+    pub account_bytes_generated: u64,
+    pub account_bytes_dispatched: u64,
+    pub slots_processed: u64,
+    pub target_account_bytes_per_slot: u64,
+    pub achieved_account_bytes_per_slot: f64,
+    pub max_channel_depth: usize,
+    pub max_channel_bytes: usize,
+    pub latencies: Vec<LatencyReport>,
+    pub errors: Vec<ErrorReport>,
+    pub reload_failures: Vec<ReloadFailure>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PluginReport {
+    pub name: String,
+    pub config_file: String,
+    // only with --isolate-plugins
+    pub crashes: u64,
+    #[serde(flatten)]
+    pub build_info: PluginBuildInfo,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct MessageReport {
+    pub generated: u64,
+    pub dispatched: u64,
+    pub dropped: u64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct LatencyReport {
+    pub plugin: String,
+    pub callback: &'static str,
+    // only for update_account
+    pub account_size: Option<&'static str>,
+    pub count: u64,
+    pub p50_us: f64,
+    pub p90_us: f64,
+    pub p99_us: f64,
+    pub p99_9_us: f64,
+    pub max_us: f64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ErrorReport {
+    pub plugin: String,
+    pub callback: &'static str,
+    pub kind: &'static str,
+    pub count: u64,
+    pub last_message: String,
+}
+
+impl<'a> RunReport<'a> {
+    pub fn new(
+        args: &'a Args,
+        started_at: String,
+        duration: Duration,
+        aborted: bool,
+        dispatcher: &PluginDispatcher,
+        stats: &DispatchStats,
+    ) -> Self {
+        let plugins = dispatcher
+            .plugin_names()
+            .into_iter()
+            .zip(dispatcher.crashes())
+            .zip(&args.geyser_plugin_config)
+            .map(|((name, crashes), config_file)| PluginReport {
+                name: name.to_string(),
+                config_file: config_file.clone(),
+                crashes,
+                build_info: plugin_build_info(Path::new(config_file)),
+            })
+            .collect();
+
+        let messages = MockMessage::KINDS
+            .into_iter()
+            .map(|kind| {
+                let message_report = MessageReport {
+                    generated: metrics::messages_generated(kind),
+                    dispatched: stats.dispatched.get(kind).copied().unwrap_or_default(),
+                    dropped: metrics::messages_dropped(kind),
+                };
+                (kind, message_report)
+            })
+            .collect();
+
+        let latencies = dispatcher
+            .latencies()
+            .into_iter()
+            .map(|(plugin, summary)| latency_report(plugin, &summary))
+            .collect();
+
+        let errors = dispatcher
+            .errors()
+            .into_iter()
+            .map(|(plugin, callback, kind, error_count)| ErrorReport {
+                plugin: plugin.to_string(),
+                callback: callback.as_str(),
+                kind,
+                count: error_count.count,
+                last_message: error_count.last_message.clone(),
+            })
+            .collect();
+
+        Self {
+            args,
+            started_at,
+            duration_secs: duration.as_secs_f64(),
+            aborted,
+            overflowed: stats.overflowed,
+            replay_failed: stats.replay_failed,
+            plugins,
+            messages,
+            account_bytes_generated: metrics::account_bytes_generated(),
+            account_bytes_dispatched: stats.account_bytes_dispatched,
+            slots_processed: stats.slots_processed,
+            target_account_bytes_per_slot: args.account_bytes_per_slot,
+            achieved_account_bytes_per_slot: if stats.slots_processed == 0 {
+                0.0
+            } else {
+                stats.account_bytes_dispatched as f64 / stats.slots_processed as f64
+            },
+            max_channel_depth: stats.max_channel_depth,
+            max_channel_bytes: stats.max_channel_bytes,
+            latencies,
+            errors,
+            reload_failures: stats.reload_failures.clone(),
+        }
+    }
+
+    pub fn write_json(&self, path: &Path) -> std::io::Result<()> {
+        let mut file = std::fs::File::create(path)?;
+        serde_json::to_writer_pretty(&mut file, self)?;
+        writeln!(file)
+    }
+
+    // one "key,value" line per field of the json report, e.g. messages.account.dropped,0
+    pub fn write_csv(&self, path: &Path) -> std::io::Result<()> {
+        let mut file = std::fs::File::create(path)?;
+        writeln!(file, "key,value")?;
+        for (key, value) in flatten(&serde_json::to_value(self)?) {
+            writeln!(file, "{},{}", key, csv_field(&value))?;
+        }
+        Ok(())
+    }
+}
+
+fn latency_report(plugin: &str, summary: &LatencySummary) -> LatencyReport {
+    let micros = |latency: Duration| latency.as_secs_f64() * 1_000_000.0;
+    LatencyReport {
+        plugin: plugin.to_string(),
+        callback: summary.callback.as_str(),
+        account_size: summary.account_size,
+        count: summary.count,
+        p50_us: micros(summary.percentiles[0]),
+        p90_us: micros(summary.percentiles[1]),
+        p99_us: micros(summary.percentiles[2]),
+        p99_9_us: micros(summary.percentiles[3]),
+        max_us: micros(summary.max),
+    }
+}
+
+// nested keys are joined with "."; array elements get their index
+fn flatten(value: &serde_json::Value) -> Vec<(String, String)> {
+    fn walk(prefix: String, value: &serde_json::Value, rows: &mut Vec<(String, String)>) {
+        let key = |child: &str| {
+            if prefix.is_empty() {
+                child.to_string()
+            } else {
+                format!("{}.{}", prefix, child)
+            }
+        };
+        match value {
+            serde_json::Value::Object(map) => {
+                for (child, value) in map {
+                    walk(key(child), value, rows);
+                }
+            }
+            serde_json::Value::Array(values) => {
+                for (index, value) in values.iter().enumerate() {
+                    walk(key(&index.to_string()), value, rows);
+                }
+            }
+            serde_json::Value::String(string) => rows.push((prefix, string.clone())),
+            serde_json::Value::Null => rows.push((prefix, String::new())),
+            other => rows.push((prefix, other.to_string())),
+        }
+    }
+    let mut rows = vec![];
+    walk(String::new(), value, &mut rows);
+    rows
+}
+
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn flatten_report_for_csv() {
+        let value = json!({
+            "args": {"geyser_plugin_config": ["a.json", "b.json"], "seed": null},
+            "messages": {"account": {"dropped": 3}},
+            "plugins": [{"name": "yellowstone, grpc"}],
+        });
+        let rows: Vec<String> = flatten(&value)
+            .into_iter()
+            .map(|(key, value)| format!("{},{}", key, csv_field(&value)))
+            .collect();
+        assert_eq!(
+            rows,
+            vec![
+                "args.geyser_plugin_config.0,a.json",
+                "args.geyser_plugin_config.1,b.json",
+                "args.seed,",
+                "messages.account.dropped,3",
+                "plugins.0.name,\"yellowstone, grpc\"",
+            ]
+        );
+    }
+}
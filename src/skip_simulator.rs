@@ -0,0 +1,66 @@
+use rand::Rng;
+use solana_program::clock::{Slot, NUM_CONSECUTIVE_LEADER_SLOTS};
+
+// decides which slots do not produce a block
+#[derive(Debug)]
+pub struct SkipSimulator {
+    // probability to skip a single slot
+    skip_rate: f64,
+    // probability that a leader skips all slots of its leader window
+    leader_skip_rate: f64,
+    leader_window_skipped: bool,
+}
+
+impl SkipSimulator {
+    pub fn new(skip_rate: f64, leader_skip_rate: f64) -> Self {
+        assert!(
+            (0.0..=1.0).contains(&skip_rate),
+            "skip_rate must be in [0.0, 1.0]"
+        );
+        assert!(
+            (0.0..=1.0).contains(&leader_skip_rate),
+            "leader_skip_rate must be in [0.0, 1.0]"
+        );
+        Self {
+            skip_rate,
+            leader_skip_rate,
+            leader_window_skipped: false,
+        }
+    }
+
+    // must be called for consecutive slots
+    pub fn is_skipped(&mut self, slot: Slot, rng: &mut impl Rng) -> bool {
+        if slot % NUM_CONSECUTIVE_LEADER_SLOTS == 0 {
+            self.leader_window_skipped = rng.gen_bool(self.leader_skip_rate);
+        }
+        self.leader_window_skipped || rng.gen_bool(self.skip_rate)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    #[test]
+    fn no_skips() {
+        let mut rng = StdRng::seed_from_u64(42);
+        let mut simulator = SkipSimulator::new(0.0, 0.0);
+        assert!((1000..2000).all(|slot| !simulator.is_skipped(slot, &mut rng)));
+    }
+
+    #[test]
+    fn leader_window_skipped_entirely() {
+        let mut rng = StdRng::seed_from_u64(42);
+        let mut simulator = SkipSimulator::new(0.0, 0.5);
+        let skipped: Vec<bool> = (1000..2000)
+            .map(|slot| simulator.is_skipped(slot, &mut rng))
+            .collect();
+
+        assert!(skipped.contains(&true));
+        for window in skipped.chunks(NUM_CONSECUTIVE_LEADER_SLOTS as usize) {
+            assert!(window.iter().all(|skipped| *skipped == window[0]));
+        }
+    }
+}